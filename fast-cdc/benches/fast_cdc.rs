@@ -4,42 +4,44 @@ extern crate cdchunking;
 extern crate fast_cdc;
 
 use cdchunking::{Chunker, ZPAQ};
-use criterion::Criterion;
-use fast_cdc::FastCDC;
-use std::fs::File;
-use std::io::prelude::*;
+use criterion::{Criterion, Throughput};
+use fake::Fake;
+use fast_cdc::{parallel_cuts, Algorithm, ChunkerConfig, ChunkingAlgorithm};
 
 fn cdc_benchmark(c: &mut Criterion) {
-    // 1. Read File
-    let mut file = File::open("./sandbox/bin/cp").unwrap();
-    let mut data = Vec::new();
-    file.read_to_end(&mut data).unwrap();
-    let data2 = data.clone();
+    // 1. Generate data
+    let data: Vec<u8> = (0..1024 * 1024 * 8)
+        .map(|_| (0..=255).fake::<u8>())
+        .collect();
 
     // 2. Create benchmark group
     let mut group = c.benchmark_group("Content Defined Chunking algorithm");
+    group.throughput(Throughput::Bytes(data.len() as u64));
 
-    // 3. Bench fast cdc algorithm
-    group.bench_function("FastCDC", |b| {
-        b.iter(|| {
-            let chunker = Chunker::new(FastCDC {});
-            let mut result: Vec<&[u8]> = Vec::new();
-            for slice in chunker.slices(&data) {
-                result.push(slice);
-            }
-            // println!("Number of chunk {:?}", result.len());
-        })
+    // 3. Bench chunking algorithms
+    for algorithm in [
+        Algorithm::FastCDC2016,
+        Algorithm::FastCDC2020,
+        Algorithm::Rabin,
+        Algorithm::Buzhash,
+    ] {
+        let cdc = algorithm.build(ChunkerConfig::default());
+        group.bench_function(format!("{:?}", algorithm), |b| {
+            b.iter(|| cdc.slices(&data).count())
+        });
+    }
+
+    // 4. Bench parallel chunking
+    let cdc = Algorithm::default().build(ChunkerConfig::default());
+    group.bench_function("FastCDC2016 parallel", |b| {
+        b.iter(|| parallel_cuts(&cdc, &data, 8).len())
     });
 
-    // 3. Bench ZPAQ algorithm
+    // 5. Bench ZPAQ algorithm
     group.bench_function("ZPAQ", |b| {
         b.iter(|| {
             let chunker = Chunker::new(ZPAQ::new(13)); // 13 bits = 8 KiB block average
-            let mut result: Vec<&[u8]> = Vec::new();
-            for slice in chunker.slices(&data2) {
-                result.push(slice);
-            }
-            // println!("Number of chunk {:?}", result.len());
+            chunker.slices(&data).count()
         })
     });
 
@@ -0,0 +1,78 @@
+use crate::{ChunkerConfig, ChunkingAlgorithm, GearTable};
+
+/// Number of bytes of the sliding window
+const WINDOW_SIZE: usize = 64;
+
+/// Buzhash (cyclic polynomial) content defined chunking
+///
+/// The hash of a 64 bytes sliding window is updated with rotations and xors
+/// of the gear table values, and a cut point is found when its low bits are
+/// zero. Like [`FastCDC`](crate::FastCDC), the mask is normalized around the
+/// average size.
+#[derive(Debug, Default, Clone)]
+pub struct Buzhash {
+    config: ChunkerConfig,
+    gear: GearTable,
+}
+
+impl Buzhash {
+    pub fn new(config: ChunkerConfig) -> Self {
+        Self::with_gear(config, GearTable::default())
+    }
+
+    /// Create a Buzhash using a specific gear table
+    pub fn with_gear(config: ChunkerConfig, gear: GearTable) -> Self {
+        Self { config, gear }
+    }
+}
+
+impl ChunkingAlgorithm for Buzhash {
+    #[inline]
+    fn config(&self) -> &ChunkerConfig {
+        &self.config
+    }
+
+    fn cut(&self, buffer: &[u8]) -> usize {
+        let min_size = self.config.min_size();
+        let mut n = buffer.len();
+        let mut avg_size = self.config.avg_size();
+        let mask_small = self.config.low_mask_small();
+        let mask_large = self.config.low_mask_large();
+
+        if n <= min_size {
+            return n;
+        }
+
+        if n >= self.config.max_size() {
+            n = self.config.max_size();
+        } else if n <= avg_size {
+            avg_size = n;
+        }
+
+        // the hash only depends on the window, so start hashing one window
+        // before the min size
+        let start = min_size.saturating_sub(WINDOW_SIZE);
+        let mut hash: u64 = 0;
+        for i in start..n {
+            hash = hash.rotate_left(1) ^ self.gear.get(buffer[i]);
+            if i >= start + WINDOW_SIZE {
+                let out = self.gear.get(buffer[i - WINDOW_SIZE]);
+                hash ^= out.rotate_left(WINDOW_SIZE as u32);
+            }
+
+            let len = i + 1;
+            if len >= min_size {
+                let mask = if len < avg_size {
+                    mask_small
+                } else {
+                    mask_large
+                };
+                if hash & mask == 0 {
+                    return len;
+                }
+            }
+        }
+
+        n
+    }
+}
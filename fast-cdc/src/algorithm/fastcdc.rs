@@ -0,0 +1,84 @@
+use crate::{ChunkerConfig, ChunkingAlgorithm, GearTable};
+use cdchunking::ChunkerImpl;
+
+/// FastCDC content defined chunking algorithm
+///
+/// Implements the normalized chunking of the [FastCDC paper], see
+/// [`Normalization`](crate::Normalization).
+///
+/// [FastCDC paper]: https://www.usenix.org/system/files/conference/atc16/atc16-paper-xia.pdf
+#[derive(Debug, Default, Clone)]
+pub struct FastCDC {
+    config: ChunkerConfig,
+    gear: GearTable,
+}
+
+impl FastCDC {
+    pub fn new(config: ChunkerConfig) -> Self {
+        Self::with_gear(config, GearTable::default())
+    }
+
+    /// Create a FastCDC using a specific gear table
+    pub fn with_gear(config: ChunkerConfig, gear: GearTable) -> Self {
+        Self { config, gear }
+    }
+
+    /// Create a FastCDC with a gear table derived from a secret key
+    ///
+    /// Chunk boundaries can't be predicted without the key, see [`GearTable`].
+    pub fn keyed(config: ChunkerConfig, key: &[u8; 32]) -> Self {
+        Self::with_gear(config, GearTable::from_key(key))
+    }
+}
+
+impl ChunkingAlgorithm for FastCDC {
+    #[inline]
+    fn config(&self) -> &ChunkerConfig {
+        &self.config
+    }
+
+    fn cut(&self, buffer: &[u8]) -> usize {
+        let mut fp: u64 = 0;
+        let mut i = self.config.min_size();
+        let mut n = buffer.len();
+        let mut avg_size = self.config.avg_size();
+        let mask_small = self.config.mask_small();
+        let mask_large = self.config.mask_large();
+
+        if n <= self.config.min_size() {
+            return n;
+        }
+
+        if n >= self.config.max_size() {
+            n = self.config.max_size();
+        } else if n <= avg_size {
+            avg_size = n;
+        }
+
+        while i < avg_size {
+            fp = (fp << 1).wrapping_add(self.gear.get(buffer[i]));
+            if fp & mask_small == 0 {
+                return i;
+            }
+            i += 1;
+        }
+
+        while i < n {
+            fp = (fp << 1).wrapping_add(self.gear.get(buffer[i]));
+            if fp & mask_large == 0 {
+                return i;
+            }
+            i += 1;
+        }
+
+        i
+    }
+}
+
+impl ChunkerImpl for FastCDC {
+    fn find_boundary(&mut self, data: &[u8]) -> Option<usize> {
+        Some(self.cut(data) - 1)
+    }
+
+    fn reset(&mut self) {}
+}
@@ -0,0 +1,96 @@
+use crate::{ChunkerConfig, ChunkingAlgorithm, GearTable};
+
+/// FastCDC rolling two bytes each time, from the [FastCDC 2020 paper]
+///
+/// The gear hash is updated two bytes at a time with a pre-shifted table and
+/// pre-shifted masks, which halves the number of shifts. Cut points are the
+/// same as [`FastCDC`](crate::FastCDC) with the same config and table.
+///
+/// [FastCDC 2020 paper]: https://ieeexplore.ieee.org/document/9055082
+#[derive(Debug, Clone)]
+pub struct FastCDC2020 {
+    config: ChunkerConfig,
+    gear: GearTable,
+    gear_ls: GearTable, // gear table shifted left by one
+}
+
+impl FastCDC2020 {
+    pub fn new(config: ChunkerConfig) -> Self {
+        Self::with_gear(config, GearTable::default())
+    }
+
+    /// Create a FastCDC2020 using a specific gear table
+    pub fn with_gear(config: ChunkerConfig, gear: GearTable) -> Self {
+        let gear_ls = gear.shifted(1);
+        Self {
+            config,
+            gear,
+            gear_ls,
+        }
+    }
+
+    /// Search a cut point in `buffer[i..end]` using `mask`, returns true when
+    /// a cut point is found at `i`
+    #[inline(always)]
+    fn roll(&self, buffer: &[u8], i: &mut usize, end: usize, fp: &mut u64, mask: u64) -> bool {
+        let mask_ls = mask << 1;
+        while *i + 1 < end {
+            *fp = (*fp << 2).wrapping_add(self.gear_ls.get(buffer[*i]));
+            if *fp & mask_ls == 0 {
+                return true;
+            }
+            *fp = fp.wrapping_add(self.gear.get(buffer[*i + 1]));
+            if *fp & mask == 0 {
+                *i += 1;
+                return true;
+            }
+            *i += 2;
+        }
+        if *i < end {
+            *fp = (*fp << 1).wrapping_add(self.gear.get(buffer[*i]));
+            if *fp & mask == 0 {
+                return true;
+            }
+            *i += 1;
+        }
+        false
+    }
+}
+
+impl Default for FastCDC2020 {
+    fn default() -> Self {
+        Self::new(ChunkerConfig::default())
+    }
+}
+
+impl ChunkingAlgorithm for FastCDC2020 {
+    #[inline]
+    fn config(&self) -> &ChunkerConfig {
+        &self.config
+    }
+
+    fn cut(&self, buffer: &[u8]) -> usize {
+        let mut fp: u64 = 0;
+        let mut i = self.config.min_size();
+        let mut n = buffer.len();
+        let mut avg_size = self.config.avg_size();
+
+        if n <= self.config.min_size() {
+            return n;
+        }
+
+        if n >= self.config.max_size() {
+            n = self.config.max_size();
+        } else if n <= avg_size {
+            avg_size = n;
+        }
+
+        if self.roll(buffer, &mut i, avg_size, &mut fp, self.config.mask_small()) {
+            return i;
+        }
+        if self.roll(buffer, &mut i, n, &mut fp, self.config.mask_large()) {
+            return i;
+        }
+        i
+    }
+}
@@ -0,0 +1,226 @@
+mod buzhash;
+mod fastcdc;
+mod fastcdc2020;
+mod rabin;
+
+pub use buzhash::Buzhash;
+pub use fastcdc::FastCDC;
+pub use fastcdc2020::FastCDC2020;
+pub use rabin::Rabin;
+
+use crate::rechunk::rechunk;
+use crate::{ChunkIter, ChunkRef, ChunkerConfig, ConfigError, Edit, GearTable, SliceChunks};
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::io::{Read, Result as IoResult, Seek};
+use std::sync::Arc;
+
+/// A content defined chunking algorithm
+///
+/// Implementations must be deterministic: the same config, table and data
+/// always give the same cut points, whatever the way data is buffered.
+pub trait ChunkingAlgorithm: Debug + Send + Sync {
+    /// Chunk size parameters
+    fn config(&self) -> &ChunkerConfig;
+
+    /// Find the first cut point of `buffer`
+    ///
+    /// Only the first `max_size` bytes of `buffer` are read, a buffer shorter
+    /// than `max_size` is considered as the end of the data.
+    fn cut(&self, buffer: &[u8]) -> usize;
+
+    /// Iterate over the chunks of a slice, without copying data
+    fn slices<'a>(&self, data: &'a [u8]) -> SliceChunks<'a, Self>
+    where
+        Self: Sized + Clone,
+    {
+        SliceChunks::new(data, self.clone())
+    }
+
+    /// Iterate over the chunks read from `src`
+    fn chunks<R: Read>(&self, src: R) -> ChunkIter<R, Self>
+    where
+        Self: Sized + Clone,
+    {
+        ChunkIter::new(src, self.clone())
+    }
+
+    /// Re-chunk a content after an edit, reusing the old chunks when possible.
+    ///
+    /// `old` are the chunk lengths of the content before the edit, and `src`
+    /// the content after the edit. Chunking restarts from the last boundary
+    /// which isn't affected by the edit, and stops as soon as a boundary
+    /// resynchronizes with the old chunk list.
+    ///
+    /// Returns the whole new chunk list, the same as chunking the new content
+    /// from scratch.
+    fn rechunk<R: Read + Seek>(&self, src: R, old: &[usize], edit: Edit) -> IoResult<Vec<ChunkRef>>
+    where
+        Self: Sized + Clone,
+    {
+        rechunk(self, src, old, edit)
+    }
+}
+
+impl<A: ChunkingAlgorithm + ?Sized> ChunkingAlgorithm for Arc<A> {
+    #[inline]
+    fn config(&self) -> &ChunkerConfig {
+        (**self).config()
+    }
+
+    #[inline]
+    fn cut(&self, buffer: &[u8]) -> usize {
+        (**self).cut(buffer)
+    }
+}
+
+impl<A: ChunkingAlgorithm + ?Sized> ChunkingAlgorithm for Box<A> {
+    #[inline]
+    fn config(&self) -> &ChunkerConfig {
+        (**self).config()
+    }
+
+    #[inline]
+    fn cut(&self, buffer: &[u8]) -> usize {
+        (**self).cut(buffer)
+    }
+}
+
+/// Chunking algorithm code.
+///
+/// The code is stored with the data (e.g. in a repository config), so the
+/// data keeps being chunked the same way when the default algorithm changes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u8", into = "u8")
+)]
+pub enum Algorithm {
+    /// [`FastCDC`], from the 2016 paper
+    #[default]
+    FastCDC2016 = 0x01,
+    /// [`FastCDC2020`], rolling two bytes each time
+    FastCDC2020 = 0x02,
+    /// [`Rabin`] fingerprint over a sliding window
+    Rabin = 0x03,
+    /// [`Buzhash`] cyclic polynomial over a sliding window
+    Buzhash = 0x04,
+}
+
+impl Algorithm {
+    /// Create the algorithm using the default (public) table
+    pub fn build(self, config: ChunkerConfig) -> Arc<dyn ChunkingAlgorithm> {
+        self.with_gear(config, GearTable::default())
+    }
+
+    /// Create the algorithm using a specific table, e.g. derived from a key
+    /// with [`GearTable::from_key`]
+    pub fn with_gear(self, config: ChunkerConfig, gear: GearTable) -> Arc<dyn ChunkingAlgorithm> {
+        match self {
+            Self::FastCDC2016 => Arc::new(FastCDC::with_gear(config, gear)),
+            Self::FastCDC2020 => Arc::new(FastCDC2020::with_gear(config, gear)),
+            Self::Rabin => Arc::new(Rabin::with_gear(config, gear)),
+            Self::Buzhash => Arc::new(Buzhash::with_gear(config, gear)),
+        }
+    }
+}
+
+impl TryFrom<u8> for Algorithm {
+    type Error = ConfigError;
+
+    fn try_from(raw: u8) -> Result<Self, Self::Error> {
+        match raw {
+            0x01 => Ok(Self::FastCDC2016),
+            0x02 => Ok(Self::FastCDC2020),
+            0x03 => Ok(Self::Rabin),
+            0x04 => Ok(Self::Buzhash),
+            _ => Err(ConfigError::InvalidAlgorithm(raw)),
+        }
+    }
+}
+
+impl From<Algorithm> for u8 {
+    fn from(algorithm: Algorithm) -> Self {
+        algorithm as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::ChunkLens;
+    use crate::Chunker;
+    use fake::Fake;
+    use std::io::Write;
+
+    const ALGORITHMS: [Algorithm; 4] = [
+        Algorithm::FastCDC2016,
+        Algorithm::FastCDC2020,
+        Algorithm::Rabin,
+        Algorithm::Buzhash,
+    ];
+
+    #[test]
+    fn algorithm_codes() {
+        for algorithm in ALGORITHMS {
+            assert_eq!(Algorithm::try_from(u8::from(algorithm)), Ok(algorithm));
+        }
+        assert_eq!(
+            Algorithm::try_from(0),
+            Err(ConfigError::InvalidAlgorithm(0))
+        );
+    }
+
+    #[test]
+    fn algorithms_chunking() {
+        let data: Vec<u8> = (0..1024 * 512).map(|_| (0..=255).fake::<u8>()).collect();
+        let config = ChunkerConfig::default();
+
+        for algorithm in ALGORITHMS {
+            let cdc = algorithm.build(config);
+            let lens: Vec<usize> = cdc.slices(&data).map(|chunk| chunk.len()).collect();
+            assert_eq!(lens.iter().sum::<usize>(), data.len());
+            assert!(lens.iter().all(|len| *len <= config.max_size()));
+            assert!(lens[..lens.len() - 1]
+                .iter()
+                .all(|len| *len >= config.min_size()));
+            // roughly the expected average size
+            let avg = data.len() / lens.len();
+            assert!(avg > config.avg_size() / 2 && avg < config.avg_size() * 2);
+
+            // boundaries are content defined
+            let shifted: Vec<usize> = cdc.slices(&data[1000..]).map(|c| c.len()).collect();
+            assert_eq!(lens[lens.len() - 10..], shifted[shifted.len() - 10..]);
+
+            let mut chunker = Chunker::with_cdc(ChunkLens::default(), cdc.clone());
+            for part in data.chunks(10_000) {
+                chunker.write(part).unwrap();
+            }
+            chunker.flush().unwrap();
+            assert_eq!(chunker.into_owned().0, lens);
+
+            let keyed = algorithm.with_gear(config, GearTable::from_key(&[3u8; 32]));
+            let keyed_lens: Vec<usize> = keyed.slices(&data).map(|c| c.len()).collect();
+            assert_ne!(lens, keyed_lens);
+        }
+    }
+
+    #[test]
+    fn fastcdc_2020_matches_2016() {
+        let data: Vec<u8> = (0..1024 * 256).map(|_| (0..=255).fake::<u8>()).collect();
+        for config in [
+            ChunkerConfig::default(),
+            ChunkerConfig::new(333, 1001, 4097).unwrap(),
+        ] {
+            let v2016 = FastCDC::new(config);
+            let v2020 = FastCDC2020::new(config);
+            let mut pos = 0;
+            while pos < data.len() {
+                let cut_pos = v2016.cut(&data[pos..]);
+                assert_eq!(v2020.cut(&data[pos..]), cut_pos);
+                pos += cut_pos;
+            }
+        }
+    }
+}
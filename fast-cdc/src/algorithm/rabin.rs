@@ -0,0 +1,153 @@
+use crate::{ChunkerConfig, ChunkingAlgorithm, GearTable};
+use std::sync::Arc;
+
+/// Irreducible polynomial of degree 53
+const POLYNOMIAL: u64 = 0x003d_a335_8b4d_c173;
+
+/// Number of bytes of the sliding window
+const WINDOW_SIZE: usize = 64;
+
+/// Degree of a polynomial, `p` must not be 0
+#[inline]
+fn degree(p: u64) -> u32 {
+    63 - p.leading_zeros()
+}
+
+/// Remainder of the division of `x` by `p` in GF(2)
+fn modulo(mut x: u64, p: u64) -> u64 {
+    while x != 0 && degree(x) >= degree(p) {
+        x ^= p << (degree(x) - degree(p));
+    }
+    x
+}
+
+#[derive(Debug)]
+struct Tables {
+    out: [u64; 256],    // fingerprint of a byte leaving the window
+    reduce: [u64; 256], // reduction of the top byte of the fingerprint
+    sbox: [u8; 256],    // byte substitution, identity when not keyed
+}
+
+impl Tables {
+    fn new(sbox: [u8; 256]) -> Self {
+        let shift = degree(POLYNOMIAL);
+        let mut out = [0u64; 256];
+        let mut reduce = [0u64; 256];
+        for byte in 0..256u64 {
+            let mut hash = modulo(byte, POLYNOMIAL);
+            for _ in 0..WINDOW_SIZE - 1 {
+                hash = modulo(hash << 8, POLYNOMIAL);
+            }
+            out[byte as usize] = hash;
+            reduce[byte as usize] = modulo(byte << shift, POLYNOMIAL) | (byte << shift);
+        }
+        Self { out, reduce, sbox }
+    }
+}
+
+/// Rabin fingerprint content defined chunking
+///
+/// The fingerprint of a 64 bytes sliding window is computed modulo an
+/// irreducible polynomial, and a cut point is found when its low bits are
+/// zero. Like [`FastCDC`](crate::FastCDC), the mask is normalized around the
+/// average size.
+///
+/// The fingerprint has no table to key, a keyed variant substitutes the
+/// input bytes with a permutation derived from the gear table.
+#[derive(Debug, Clone)]
+pub struct Rabin {
+    config: ChunkerConfig,
+    tables: Arc<Tables>,
+}
+
+impl Rabin {
+    pub fn new(config: ChunkerConfig) -> Self {
+        let mut sbox = [0u8; 256];
+        for (i, byte) in sbox.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        Self {
+            config,
+            tables: Arc::new(Tables::new(sbox)),
+        }
+    }
+
+    /// Create a Rabin chunker with an input permutation derived from `gear`
+    pub fn with_gear(config: ChunkerConfig, gear: GearTable) -> Self {
+        if gear == GearTable::default() {
+            return Self::new(config);
+        }
+
+        // sort bytes by their gear value
+        let mut order: Vec<u8> = (0..=255).collect();
+        order.sort_by_key(|byte| (gear.get(*byte), *byte));
+        let mut sbox = [0u8; 256];
+        for (i, byte) in order.into_iter().enumerate() {
+            sbox[byte as usize] = i as u8;
+        }
+        Self {
+            config,
+            tables: Arc::new(Tables::new(sbox)),
+        }
+    }
+}
+
+impl Default for Rabin {
+    fn default() -> Self {
+        Self::new(ChunkerConfig::default())
+    }
+}
+
+impl ChunkingAlgorithm for Rabin {
+    #[inline]
+    fn config(&self) -> &ChunkerConfig {
+        &self.config
+    }
+
+    fn cut(&self, buffer: &[u8]) -> usize {
+        let tables = &*self.tables;
+        let shift = degree(POLYNOMIAL) - 8;
+        let min_size = self.config.min_size();
+        let mut n = buffer.len();
+        let mut avg_size = self.config.avg_size();
+        let mask_small = self.config.low_mask_small();
+        let mask_large = self.config.low_mask_large();
+
+        if n <= min_size {
+            return n;
+        }
+
+        if n >= self.config.max_size() {
+            n = self.config.max_size();
+        } else if n <= avg_size {
+            avg_size = n;
+        }
+
+        // the fingerprint only depends on the window, so start hashing one
+        // window before the min size
+        let mut window = [0u8; WINDOW_SIZE];
+        let mut digest: u64 = 0;
+        for (w, i) in (min_size.saturating_sub(WINDOW_SIZE)..n).enumerate() {
+            let byte = tables.sbox[buffer[i] as usize];
+            let wpos = w % WINDOW_SIZE;
+            digest ^= tables.out[window[wpos] as usize];
+            window[wpos] = byte;
+            let top = (digest >> shift) as usize;
+            digest = ((digest << 8) | byte as u64) ^ tables.reduce[top];
+
+            let len = i + 1;
+            if len >= min_size {
+                let mask = if len < avg_size {
+                    mask_small
+                } else {
+                    mask_large
+                };
+                if digest & mask == 0 {
+                    return len;
+                }
+            }
+        }
+
+        n
+    }
+}
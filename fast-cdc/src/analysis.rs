@@ -0,0 +1,199 @@
+//! Deduplication analysis
+//!
+//! Chunk a set of files and count the bytes which would be stored once
+//! deduplicated, to compare chunking parameters on real data.
+use crate::presplit::ArchiveChunks;
+use crate::ChunkingAlgorithm;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::{self, File};
+use std::io::{Read, Result as IoResult};
+use std::path::{Path, PathBuf};
+
+/// Dedup statistics of a single file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub size: u64,
+    pub chunks: u64,
+    pub unique_bytes: u64, // bytes of the chunks not seen before
+}
+
+impl FileReport {
+    /// Ratio of the file bytes already stored by a previous chunk
+    pub fn dedup_ratio(&self) -> f64 {
+        ratio(self.size - self.unique_bytes, self.size)
+    }
+}
+
+/// Dedup statistics of all analyzed files
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DedupReport {
+    pub total_bytes: u64,
+    pub unique_bytes: u64,
+    pub chunks: u64,
+    pub unique_chunks: u64,
+    /// Number of chunks by size, keyed by the power of two lower bound
+    pub histogram: BTreeMap<usize, u64>,
+    pub files: Vec<FileReport>,
+}
+
+impl DedupReport {
+    /// Ratio of the bytes saved by deduplication
+    pub fn dedup_ratio(&self) -> f64 {
+        ratio(self.total_bytes - self.unique_bytes, self.total_bytes)
+    }
+
+    /// Average chunk size
+    pub fn avg_chunk_size(&self) -> u64 {
+        self.total_bytes.checked_div(self.chunks).unwrap_or(0)
+    }
+}
+
+impl Display for DedupReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        writeln!(f, "files         {}", self.files.len())?;
+        writeln!(f, "total bytes   {}", self.total_bytes)?;
+        writeln!(f, "unique bytes  {}", self.unique_bytes)?;
+        writeln!(f, "dedup ratio   {:.2}%", self.dedup_ratio() * 100.0)?;
+        writeln!(f, "chunks        {}", self.chunks)?;
+        writeln!(f, "unique chunks {}", self.unique_chunks)?;
+        writeln!(f, "avg chunk     {}", self.avg_chunk_size())?;
+        writeln!(f, "chunk size histogram")?;
+        for (size, count) in &self.histogram {
+            writeln!(f, "  >= {:>9} {:>9}", size, count)?;
+        }
+        Ok(())
+    }
+}
+
+fn ratio(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+/// Chunk files and collect their dedup statistics
+///
+/// ```
+/// use fast_cdc::analysis::DedupAnalyzer;
+/// use fast_cdc::FastCDC;
+///
+/// let data = vec![42u8; 100 * 1024];
+/// let mut analyzer = DedupAnalyzer::new(FastCDC::default());
+/// analyzer.add_reader("a", &data[..]).unwrap();
+/// analyzer.add_reader("b", &data[..]).unwrap();
+/// let report = analyzer.report();
+/// assert_eq!(report.total_bytes, 2 * data.len() as u64);
+/// assert_eq!(report.unique_bytes, data.len() as u64); // "b" is a duplicate
+/// ```
+#[derive(Debug)]
+pub struct DedupAnalyzer<A: ChunkingAlgorithm + Clone> {
+    cdc: A,
+    seen: HashSet<[u8; 32]>, // blake3 hashes of the chunks already stored
+    report: DedupReport,
+    presplit: bool, // cut at archive member boundaries
+}
+
+impl<A: ChunkingAlgorithm + Clone> DedupAnalyzer<A> {
+    pub fn new(cdc: A) -> Self {
+        Self {
+            cdc,
+            seen: HashSet::new(),
+            report: DedupReport::default(),
+            presplit: false,
+        }
+    }
+
+    /// Force a cut point at each archive member boundary, see
+    /// [`presplit`](crate::presplit)
+    ///
+    /// Files are then read in memory before chunking.
+    #[inline]
+    pub fn set_presplit(&mut self, presplit: bool) {
+        self.presplit = presplit;
+    }
+
+    /// Chunk the content read from `src`
+    pub fn add_reader<P: Into<PathBuf>, R: Read>(
+        &mut self,
+        path: P,
+        mut src: R,
+    ) -> IoResult<&FileReport> {
+        let mut file = FileReport {
+            path: path.into(),
+            size: 0,
+            chunks: 0,
+            unique_bytes: 0,
+        };
+
+        if self.presplit {
+            let mut data = Vec::new();
+            src.read_to_end(&mut data)?;
+            for chunk in ArchiveChunks::new(&data, self.cdc.clone()) {
+                self.add_chunk(&mut file, chunk.data);
+            }
+        } else {
+            for chunk in self.cdc.chunks(src) {
+                self.add_chunk(&mut file, &chunk?.data);
+            }
+        }
+
+        self.report.total_bytes += file.size;
+        self.report.unique_bytes += file.unique_bytes;
+        self.report.chunks += file.chunks;
+        self.report.files.push(file);
+        Ok(self.report.files.last().unwrap())
+    }
+
+    fn add_chunk(&mut self, file: &mut FileReport, chunk: &[u8]) {
+        let len = chunk.len() as u64;
+        file.size += len;
+        file.chunks += 1;
+
+        let bucket = 1 << (usize::BITS - 1 - chunk.len().leading_zeros());
+        *self.report.histogram.entry(bucket).or_insert(0) += 1;
+
+        if self.seen.insert(*blake3::hash(chunk).as_bytes()) {
+            file.unique_bytes += len;
+            self.report.unique_chunks += 1;
+        }
+    }
+
+    /// Chunk a local file
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> IoResult<&FileReport> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        self.add_reader(path, file)
+    }
+
+    /// Chunk all files of a local directory, recursively
+    ///
+    /// Files are visited in path order, symbolic links aren't followed.
+    pub fn add_dir<P: AsRef<Path>>(&mut self, path: P) -> IoResult<()> {
+        let mut entries = fs::read_dir(path)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<IoResult<Vec<_>>>()?;
+        entries.sort();
+
+        for entry in entries {
+            let file_type = fs::symlink_metadata(&entry)?.file_type();
+            if file_type.is_dir() {
+                self.add_dir(&entry)?;
+            } else if file_type.is_file() {
+                self.add_file(&entry)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn report(&self) -> &DedupReport {
+        &self.report
+    }
+
+    pub fn into_report(self) -> DedupReport {
+        self.report
+    }
+}
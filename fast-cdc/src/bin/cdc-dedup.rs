@@ -0,0 +1,106 @@
+//! Measure the dedup ratio of a directory
+//!
+//! Usage: cdc-dedup [--min SIZE] [--avg SIZE] [--max SIZE] [--level 0-3]
+//!                  [--algorithm fastcdc2016|fastcdc2020|rabin|buzhash]
+//!                  [--presplit] [--files] <DIR>...
+extern crate fast_cdc;
+
+use fast_cdc::analysis::DedupAnalyzer;
+use fast_cdc::{Algorithm, ChunkerConfig, Normalization};
+use std::convert::TryFrom;
+use std::env;
+use std::process::exit;
+
+const USAGE: &str = "Usage: cdc-dedup [--min SIZE] [--avg SIZE] [--max SIZE] [--level 0-3] \
+[--algorithm fastcdc2016|fastcdc2020|rabin|buzhash] [--presplit] [--files] <DIR>...";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}\n{}", message, USAGE);
+    exit(2);
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: Option<String>) -> T {
+    match value.map(|value| value.parse()) {
+        Some(Ok(value)) => value,
+        _ => fail(&format!("invalid value for {}", name)),
+    }
+}
+
+fn parse_algorithm(value: Option<String>) -> Algorithm {
+    match value.as_deref() {
+        Some("fastcdc2016") | Some("fastcdc") => Algorithm::FastCDC2016,
+        Some("fastcdc2020") => Algorithm::FastCDC2020,
+        Some("rabin") => Algorithm::Rabin,
+        Some("buzhash") => Algorithm::Buzhash,
+        _ => fail("invalid value for --algorithm"),
+    }
+}
+
+fn main() {
+    let default = ChunkerConfig::default();
+    let (mut min, mut avg, mut max) = (default.min_size(), default.avg_size(), default.max_size());
+    let mut level = default.normalization();
+    let mut algorithm = Algorithm::default();
+    let mut presplit = false;
+    let mut show_files = false;
+    let mut dirs = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--min" => min = parse_number("--min", args.next()),
+            "--avg" => avg = parse_number("--avg", args.next()),
+            "--max" => max = parse_number("--max", args.next()),
+            "--level" => {
+                level = Normalization::try_from(parse_number::<u8>("--level", args.next()))
+                    .unwrap_or_else(|err| fail(&err.to_string()))
+            }
+            "--algorithm" => algorithm = parse_algorithm(args.next()),
+            "--presplit" => presplit = true,
+            "--files" => show_files = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return;
+            }
+            _ if arg.starts_with('-') => fail(&format!("unknown option {}", arg)),
+            _ => dirs.push(arg),
+        }
+    }
+    if dirs.is_empty() {
+        fail("missing directory");
+    }
+
+    let mut config = ChunkerConfig::new(min, avg, max).unwrap_or_else(|err| fail(&err.to_string()));
+    config.set_normalization(level);
+
+    let mut analyzer = DedupAnalyzer::new(algorithm.build(config));
+    analyzer.set_presplit(presplit);
+    for dir in &dirs {
+        if let Err(err) = analyzer.add_dir(dir) {
+            eprintln!("{}: {}", dir, err);
+            exit(1);
+        }
+    }
+
+    let report = analyzer.into_report();
+    println!("algorithm     {:?}", algorithm);
+    println!(
+        "chunk sizes   {}/{}/{} level {}",
+        min,
+        avg,
+        max,
+        u8::from(level)
+    );
+    print!("{}", report);
+    if show_files {
+        println!("files");
+        for file in &report.files {
+            println!(
+                "  {:>6.2}% {:>12} {}",
+                file.dedup_ratio() * 100.0,
+                file.size,
+                file.path.display()
+            );
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+/// Smallest `min_size` accepted by a [`ChunkerConfig`].
+pub const MINIMUM_MIN_SIZE: usize = 64; // 64 B
+
+/// Largest `max_size` accepted by a [`ChunkerConfig`].
+pub const MAXIMUM_MAX_SIZE: usize = 1024 * 1024 * 16; // 16 MB
+
+/// Smallest `avg_size` accepted by a [`ChunkerConfig`].
+pub const MINIMUM_AVG_SIZE: usize = 256; // 256 B
+
+/// Largest `avg_size` accepted by a [`ChunkerConfig`].
+pub const MAXIMUM_AVG_SIZE: usize = 1024 * 1024 * 4; // 4 MB
+
+// Empirically derived values where the padded zero bits are almost evenly
+// distributed for slightly higher deduplication ratio according to our
+// large scale tests. Masks are indexed by their number of '1' bits.
+const MASKS: [u64; 26] = [
+    0,                  // padding
+    0,                  // padding
+    0,                  // padding
+    0,                  // padding
+    0,                  // padding
+    0x0000000001804110, // 32 B
+    0x0000000001803110, // 64 B
+    0x0000000018035100, // 128 B
+    0x0000001800035300, // 256 B
+    0x0000019000353000, // 512 B
+    0x0000590003530000, // 1 KB
+    0x0000d90003530000, // 2 KB
+    0x0000d90103530000, // 4 KB
+    0x0000d90303530000, // 8 KB
+    0x0000d90313530000, // 16 KB
+    0x0000d90f03530000, // 32 KB
+    0x0000d90303537000, // 64 KB
+    0x0000d90703537000, // 128 KB
+    0x0000d90707537000, // 256 KB
+    0x0000d91707537000, // 512 KB
+    0x0000d91747537000, // 1 MB
+    0x0000d91767537000, // 2 MB
+    0x0000d93767537000, // 4 MB
+    0x0000d93777537000, // 8 MB
+    0x0000d93777577000, // 16 MB
+    0x0000db3777577000, // 32 MB
+];
+
+/// Errors returned when validating a [`ChunkerConfig`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigError {
+    /// `min_size` is lower than [`MINIMUM_MIN_SIZE`]
+    MinSizeTooSmall(usize),
+    /// `avg_size` is outside of [`MINIMUM_AVG_SIZE`]..=[`MAXIMUM_AVG_SIZE`]
+    AvgSizeOutOfRange(usize),
+    /// `max_size` is greater than [`MAXIMUM_MAX_SIZE`]
+    MaxSizeTooLarge(usize),
+    /// sizes don't respect `min_size <= avg_size <= max_size`
+    NotOrdered,
+    /// normalization level is not in range 0..=3
+    InvalidNormalization(u8),
+    /// unknown chunking algorithm code
+    InvalidAlgorithm(u8),
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::MinSizeTooSmall(size) => {
+                write!(f, "min size {} is lower than {}", size, MINIMUM_MIN_SIZE)
+            }
+            Self::AvgSizeOutOfRange(size) => write!(
+                f,
+                "average size {} is not in range {}..={}",
+                size, MINIMUM_AVG_SIZE, MAXIMUM_AVG_SIZE
+            ),
+            Self::MaxSizeTooLarge(size) => {
+                write!(f, "max size {} is greater than {}", size, MAXIMUM_MAX_SIZE)
+            }
+            Self::NotOrdered => write!(f, "sizes must respect min <= average <= max"),
+            Self::InvalidNormalization(level) => {
+                write!(f, "normalization level {} is not in range 0..=3", level)
+            }
+            Self::InvalidAlgorithm(code) => write!(f, "unknown chunking algorithm {:#04x}", code),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+/// FastCDC normalized chunking level.
+///
+/// Before reaching the average size, cut points are searched with a mask
+/// having `level` more '1' bits than the average mask (harder to match), and
+/// after it with a mask having `level` less '1' bits (easier to match). The
+/// higher the level, the closer the chunk sizes are to the average size.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "u8", into = "u8")
+)]
+pub enum Normalization {
+    Level0 = 0,
+    Level1 = 1,
+    #[default]
+    Level2 = 2,
+    Level3 = 3,
+}
+
+impl TryFrom<u8> for Normalization {
+    type Error = ConfigError;
+
+    fn try_from(raw: u8) -> Result<Self, Self::Error> {
+        match raw {
+            0 => Ok(Self::Level0),
+            1 => Ok(Self::Level1),
+            2 => Ok(Self::Level2),
+            3 => Ok(Self::Level3),
+            _ => Err(ConfigError::InvalidNormalization(raw)),
+        }
+    }
+}
+
+impl From<Normalization> for u8 {
+    fn from(level: Normalization) -> Self {
+        level as u8
+    }
+}
+
+/// Chunk size parameters.
+///
+/// Chunks are never smaller than `min_size` (except the last one) and never
+/// larger than `max_size`. The cut-point masks are derived from `avg_size`
+/// and the [`Normalization`] level.
+///
+/// Every writer of a repository must use the same config, otherwise the same
+/// data won't produce the same chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChunkerConfig {
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
+    normalization: Normalization,
+}
+
+impl ChunkerConfig {
+    /// Create a new validated config
+    pub fn new(min_size: usize, avg_size: usize, max_size: usize) -> Result<Self, ConfigError> {
+        let config = Self {
+            min_size,
+            avg_size,
+            max_size,
+            normalization: Normalization::default(),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check the config parameters
+    ///
+    /// A config loaded from an untrusted source (e.g. deserialized) should be
+    /// validated before use.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.min_size < MINIMUM_MIN_SIZE {
+            return Err(ConfigError::MinSizeTooSmall(self.min_size));
+        }
+        if !(MINIMUM_AVG_SIZE..=MAXIMUM_AVG_SIZE).contains(&self.avg_size) {
+            return Err(ConfigError::AvgSizeOutOfRange(self.avg_size));
+        }
+        if self.max_size > MAXIMUM_MAX_SIZE {
+            return Err(ConfigError::MaxSizeTooLarge(self.max_size));
+        }
+        if self.min_size > self.avg_size || self.avg_size > self.max_size {
+            return Err(ConfigError::NotOrdered);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn min_size(&self) -> usize {
+        self.min_size
+    }
+
+    #[inline]
+    pub fn avg_size(&self) -> usize {
+        self.avg_size
+    }
+
+    #[inline]
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    #[inline]
+    pub fn normalization(&self) -> Normalization {
+        self.normalization
+    }
+
+    #[inline]
+    pub fn set_normalization(&mut self, normalization: Normalization) {
+        self.normalization = normalization;
+    }
+
+    /// Number of bits of the average size (rounded log2)
+    #[inline]
+    fn avg_bits(&self) -> usize {
+        let bits = (usize::BITS - 1 - self.avg_size.leading_zeros()) as usize;
+        // round to the nearest power of two
+        if self.avg_size - (1 << bits) >= (1 << bits) / 2 {
+            bits + 1
+        } else {
+            bits
+        }
+    }
+
+    /// Mask matching on average every `avg_size` bytes
+    #[inline]
+    pub fn mask_average(&self) -> u64 {
+        MASKS[self.avg_bits()]
+    }
+
+    /// Mask used before reaching the average size
+    #[inline]
+    pub fn mask_small(&self) -> u64 {
+        MASKS[self.avg_bits() + self.normalization as usize]
+    }
+
+    /// Mask used after reaching the average size
+    #[inline]
+    pub fn mask_large(&self) -> u64 {
+        MASKS[self.avg_bits() - self.normalization as usize]
+    }
+
+    /// Low bits mask used by rolling hashes before reaching the average size
+    #[inline]
+    pub fn low_mask_small(&self) -> u64 {
+        (1 << (self.avg_bits() + self.normalization as usize)) - 1
+    }
+
+    /// Low bits mask used by rolling hashes after reaching the average size
+    #[inline]
+    pub fn low_mask_large(&self) -> u64 {
+        (1 << (self.avg_bits() - self.normalization as usize)) - 1
+    }
+}
+
+impl Default for ChunkerConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 1024 * 2,  // 2 KB
+            avg_size: 1024 * 8,  // 8 KB
+            max_size: 1024 * 64, // 64 KB
+            normalization: Normalization::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_masks() {
+        let config = ChunkerConfig::default();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.mask_small(), 0x0000d90f03530000); // 15 '1' bits
+        assert_eq!(config.mask_average(), 0x0000d90303530000); // 13 '1' bits
+        assert_eq!(config.mask_large(), 0x0000d90003530000); // 11 '1' bits
+    }
+
+    #[test]
+    fn normalization_masks() {
+        let mut config = ChunkerConfig::default();
+        config.set_normalization(Normalization::Level0);
+        assert_eq!(config.mask_small(), config.mask_average());
+        assert_eq!(config.mask_large(), config.mask_average());
+
+        for level in 0..=3u8 {
+            let mut config = ChunkerConfig::new(64, MINIMUM_AVG_SIZE, 1024).unwrap();
+            config.set_normalization(Normalization::try_from(level).unwrap());
+            assert_eq!(config.mask_large().count_ones(), 8 - level as u32);
+            let mut config = ChunkerConfig::new(64, MAXIMUM_AVG_SIZE, MAXIMUM_MAX_SIZE).unwrap();
+            config.set_normalization(Normalization::try_from(level).unwrap());
+            assert_eq!(config.mask_small().count_ones(), 22 + level as u32);
+        }
+        assert_eq!(
+            Normalization::try_from(4),
+            Err(ConfigError::InvalidNormalization(4))
+        );
+    }
+
+    #[test]
+    fn masks_bits() {
+        for (bits, mask) in MASKS.iter().enumerate().skip(5) {
+            assert_eq!(mask.count_ones() as usize, bits);
+        }
+    }
+
+    #[test]
+    fn validate() {
+        assert!(ChunkerConfig::new(256, 1024, 4096).is_ok());
+        assert_eq!(
+            ChunkerConfig::new(16, 1024, 4096),
+            Err(ConfigError::MinSizeTooSmall(16))
+        );
+        assert_eq!(
+            ChunkerConfig::new(64, 128, 4096),
+            Err(ConfigError::AvgSizeOutOfRange(128))
+        );
+        assert_eq!(
+            ChunkerConfig::new(64, 1024, MAXIMUM_MAX_SIZE + 1),
+            Err(ConfigError::MaxSizeTooLarge(MAXIMUM_MAX_SIZE + 1))
+        );
+        assert_eq!(
+            ChunkerConfig::new(2048, 1024, 4096),
+            Err(ConfigError::NotOrdered)
+        );
+    }
+
+    #[test]
+    fn avg_rounding() {
+        let config = ChunkerConfig::new(1024, 10_000, 65536).unwrap();
+        assert_eq!(config.mask_average(), MASKS[13]);
+        let config = ChunkerConfig::new(1024, 13_000, 65536).unwrap();
+        assert_eq!(config.mask_average(), MASKS[14]);
+    }
+}
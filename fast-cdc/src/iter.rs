@@ -0,0 +1,265 @@
+use crate::{ChunkingAlgorithm, FastCDC};
+use std::io::{ErrorKind, Read, Result as IoResult};
+
+/// An owned chunk of data and its position in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub offset: u64,
+    pub data: Vec<u8>,
+}
+
+impl Chunk {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    #[inline]
+    pub fn end_offset(&self) -> u64 {
+        self.offset + self.len() as u64
+    }
+}
+
+/// A borrowed chunk of data and its position in the source slice
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkSlice<'a> {
+    pub offset: u64,
+    pub data: &'a [u8],
+}
+
+impl<'a> ChunkSlice<'a> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    #[inline]
+    pub fn end_offset(&self) -> u64 {
+        self.offset + self.len() as u64
+    }
+
+    /// Copy the chunk data
+    pub fn to_chunk(&self) -> Chunk {
+        Chunk {
+            offset: self.offset,
+            data: self.data.to_vec(),
+        }
+    }
+}
+
+/// Iterator over the chunks of a [`Read`] source
+///
+/// ```
+/// use fast_cdc::{ChunkIter, FastCDC};
+///
+/// let data = vec![42u8; 100 * 1024];
+/// let mut len = 0;
+/// for chunk in ChunkIter::new(&data[..], FastCDC::default()) {
+///     let chunk = chunk.unwrap();
+///     assert_eq!(chunk.offset, len);
+///     len += chunk.len() as u64;
+/// }
+/// assert_eq!(len, data.len() as u64);
+/// ```
+#[derive(Debug)]
+pub struct ChunkIter<R: Read, A: ChunkingAlgorithm = FastCDC> {
+    src: R,              // source reader
+    buf: ChunkBuffer<A>, // read buffer
+}
+
+impl<R: Read, A: ChunkingAlgorithm> ChunkIter<R, A> {
+    pub fn new(src: R, cdc: A) -> Self {
+        Self {
+            src,
+            buf: ChunkBuffer::new(cdc),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.src
+    }
+}
+
+impl<R: Read, A: ChunkingAlgorithm> Iterator for ChunkIter<R, A> {
+    type Item = IoResult<Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // read source until a whole max size window is buffered, or end of file
+        while self.buf.needs_data() {
+            match self.src.read(self.buf.spare()) {
+                Ok(read) => self.buf.advance(read),
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+
+        self.buf.next_chunk().map(Ok)
+    }
+}
+
+/// Buffer of source data, shared by the sync and async chunk readers
+#[derive(Debug)]
+pub(crate) struct ChunkBuffer<A: ChunkingAlgorithm> {
+    cdc: A,       // chunking algorithm
+    buf: Vec<u8>, // read buffer
+    pos: usize,   // start of the next chunk in buffer
+    len: usize,   // length of data in buffer
+    offset: u64,  // source offset of the next chunk
+    eof: bool,
+}
+
+impl<A: ChunkingAlgorithm> ChunkBuffer<A> {
+    pub(crate) fn new(cdc: A) -> Self {
+        let max_buffer_size = cdc.config().max_size() * 2;
+        Self {
+            cdc,
+            buf: vec![0u8; max_buffer_size],
+            pos: 0,
+            len: 0,
+            offset: 0,
+            eof: false,
+        }
+    }
+
+    /// A cut point is only final when a whole max size window is buffered,
+    /// or at end of file
+    #[inline]
+    pub(crate) fn needs_data(&self) -> bool {
+        !self.eof && self.len - self.pos < self.cdc.config().max_size()
+    }
+
+    /// Free space to read source data into
+    pub(crate) fn spare(&mut self) -> &mut [u8] {
+        // copy data that left in the beginning of the buffer
+        if self.pos > 0 {
+            self.buf.copy_within(self.pos..self.len, 0);
+            self.len -= self.pos;
+            self.pos = 0;
+        }
+        &mut self.buf[self.len..]
+    }
+
+    /// Commit `read` bytes read into [`spare`](Self::spare), 0 is end of file
+    #[inline]
+    pub(crate) fn advance(&mut self, read: usize) {
+        if read == 0 {
+            self.eof = true;
+        }
+        self.len += read;
+    }
+
+    /// Cut the next chunk out of the buffered data
+    pub(crate) fn next_chunk(&mut self) -> Option<Chunk> {
+        if self.pos == self.len {
+            return None;
+        }
+
+        let cut_pos = self.cdc.cut(&self.buf[self.pos..self.len]);
+        let chunk = Chunk {
+            offset: self.offset,
+            data: self.buf[self.pos..self.pos + cut_pos].to_vec(),
+        };
+        self.pos += cut_pos;
+        self.offset += cut_pos as u64;
+        Some(chunk)
+    }
+}
+
+/// Zero-copy iterator over the chunks of a slice
+#[derive(Debug, Clone)]
+pub struct SliceChunks<'a, A: ChunkingAlgorithm = FastCDC> {
+    data: &'a [u8],
+    cdc: A,
+    pos: usize,
+}
+
+impl<'a, A: ChunkingAlgorithm> SliceChunks<'a, A> {
+    pub fn new(data: &'a [u8], cdc: A) -> Self {
+        Self { data, cdc, pos: 0 }
+    }
+}
+
+impl<'a, A: ChunkingAlgorithm> Iterator for SliceChunks<'a, A> {
+    type Item = ChunkSlice<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.data.len() {
+            return None;
+        }
+
+        let cut_pos = self.cdc.cut(&self.data[self.pos..]);
+        let chunk = ChunkSlice {
+            offset: self.pos as u64,
+            data: &self.data[self.pos..self.pos + cut_pos],
+        };
+        self.pos += cut_pos;
+        Some(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ChunkerConfig;
+    use fake::Fake;
+    use std::io::{Error as IoError, Read};
+
+    /// Reader returning data in small pieces, with interruptions
+    struct SlowReader<'a> {
+        data: &'a [u8],
+        calls: usize,
+    }
+
+    impl<'a> Read for SlowReader<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+            self.calls += 1;
+            if self.calls % 3 == 0 {
+                return Err(IoError::from(ErrorKind::Interrupted));
+            }
+            let len = self.data.len().min(buf.len()).min(777);
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    #[test]
+    fn reader_matches_slice() {
+        let data: Vec<u8> = (0..1024 * 300).map(|_| (0..=255).fake::<u8>()).collect();
+        let cdc = FastCDC::new(ChunkerConfig::default());
+
+        let slices: Vec<ChunkSlice> = SliceChunks::new(&data, cdc.clone()).collect();
+        assert!(slices.len() > 1);
+        assert_eq!(slices[0].offset, 0);
+        for pair in slices.windows(2) {
+            assert_eq!(pair[0].end_offset(), pair[1].offset);
+        }
+        assert_eq!(slices.last().unwrap().end_offset(), data.len() as u64);
+
+        let reader = SlowReader {
+            data: &data,
+            calls: 0,
+        };
+        let chunks: Vec<Chunk> = ChunkIter::new(reader, cdc)
+            .collect::<IoResult<_>>()
+            .unwrap();
+        let expected: Vec<Chunk> = slices.iter().map(ChunkSlice::to_chunk).collect();
+        assert_eq!(chunks, expected);
+    }
+
+    #[test]
+    fn empty_source() {
+        assert!(ChunkIter::new(&[][..], FastCDC::default()).next().is_none());
+        assert!(SliceChunks::new(&[], FastCDC::default()).next().is_none());
+    }
+}
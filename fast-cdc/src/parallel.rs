@@ -0,0 +1,132 @@
+use crate::ChunkingAlgorithm;
+use std::thread;
+
+/// Number of max size chunks processed at least by a thread
+const SEGMENT_CHUNKS: usize = 8;
+
+/// Size of the buffer needed to keep `threads` threads busy
+pub(crate) fn buffer_size<A: ChunkingAlgorithm>(cdc: &A, threads: usize) -> usize {
+    let max_size = cdc.config().max_size();
+    (max_size * SEGMENT_CHUNKS * threads).max(max_size * 2)
+}
+
+/// Chunk `data` using up to `threads` threads, returns the chunk lengths
+///
+/// `data` is split in segments, and every thread chunks its segment as if a
+/// chunk started at the beginning of the segment. These speculative cut
+/// points are then stitched in order: the serial cut points are followed
+/// until one of them is also a speculative cut point of the next segment,
+/// from where both are the same. The result is identical to the serial
+/// chunking of `data`.
+pub fn parallel_cuts<A: ChunkingAlgorithm>(cdc: &A, data: &[u8], threads: usize) -> Vec<usize> {
+    let segment_min = cdc.config().max_size() * SEGMENT_CHUNKS;
+    let segments = threads.min(data.len() / segment_min).max(1);
+    let segment_size = data.len() / segments;
+    let bounds: Vec<(usize, usize)> = (0..segments)
+        .map(|k| {
+            let end = if k + 1 == segments {
+                data.len()
+            } else {
+                (k + 1) * segment_size
+            };
+            (k * segment_size, end)
+        })
+        .collect();
+
+    // speculative cut points of every segment, from its start until the
+    // first cut point past its end
+    let speculated: Vec<Vec<usize>> = if segments == 1 {
+        vec![speculate(cdc, data, 0, data.len())]
+    } else {
+        thread::scope(|scope| {
+            let handles: Vec<_> = bounds
+                .iter()
+                .map(|(start, end)| scope.spawn(move || speculate(cdc, data, *start, *end)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("chunking thread panicked"))
+                .collect()
+        })
+    };
+
+    // stitch segments, resynchronizing at the seams
+    let mut lens = Vec::new();
+    let mut pos = 0;
+    for ((_, end), cuts) in bounds.iter().zip(&speculated) {
+        while pos < *end {
+            if let Ok(index) = cuts.binary_search(&pos) {
+                for pair in cuts[index..].windows(2) {
+                    lens.push(pair[1] - pair[0]);
+                }
+                pos = *cuts.last().unwrap();
+                break;
+            }
+            let cut_pos = cdc.cut(&data[pos..]);
+            lens.push(cut_pos);
+            pos += cut_pos;
+        }
+    }
+    lens
+}
+
+/// Cut points from `start` until the first one reaching `end`, `start`
+/// included
+fn speculate<A: ChunkingAlgorithm>(cdc: &A, data: &[u8], start: usize, end: usize) -> Vec<usize> {
+    let mut cuts = vec![start];
+    let mut pos = start;
+    while pos < end {
+        pos += cdc.cut(&data[pos..]);
+        cuts.push(pos);
+    }
+    cuts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Algorithm, ChunkerConfig};
+
+    /// Fast pseudo random data (xorshift)
+    fn random_data(len: usize, mut seed: u64) -> Vec<u8> {
+        (0..len)
+            .map(|_| {
+                seed ^= seed << 13;
+                seed ^= seed >> 7;
+                seed ^= seed << 17;
+                (seed >> 32) as u8
+            })
+            .collect()
+    }
+
+    fn serial_cuts<A: ChunkingAlgorithm>(cdc: &A, data: &[u8]) -> Vec<usize> {
+        let mut lens = Vec::new();
+        let mut pos = 0;
+        while pos < data.len() {
+            let cut_pos = cdc.cut(&data[pos..]);
+            lens.push(cut_pos);
+            pos += cut_pos;
+        }
+        lens
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        let mut data = random_data(1024 * 1024 * 3, 42);
+        // low entropy regions only have max size chunks, so the speculative
+        // cut points can't resync with the serial ones
+        data[1024 * 700..1024 * 1500]
+            .iter_mut()
+            .for_each(|b| *b = 0);
+
+        let config = ChunkerConfig::default();
+        for algorithm in [Algorithm::FastCDC2016, Algorithm::Rabin] {
+            let cdc = algorithm.build(config);
+            let expected = serial_cuts(&cdc, &data);
+            for threads in [1, 2, 3, 8, 64] {
+                assert_eq!(parallel_cuts(&cdc, &data, threads), expected);
+            }
+            assert!(parallel_cuts(&cdc, &[], 4).is_empty());
+        }
+    }
+}
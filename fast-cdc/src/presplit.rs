@@ -0,0 +1,349 @@
+//! Format-aware pre-splitting of archives
+//!
+//! Archive headers shift the content of the members, so a member stored in
+//! two archives doesn't always give the same chunks. The pre-splitter finds
+//! the member boundaries of tar and zip archives, and forces a cut point at
+//! each of them before content defined chunking of the members.
+use crate::{ChunkSlice, ChunkingAlgorithm, FastCDC};
+
+const TAR_BLOCK_SIZE: usize = 512;
+const ZIP_LOCAL_SIGNATURE: u32 = 0x0403_4b50;
+const ZIP_CENTRAL_SIGNATURE: u32 = 0x0201_4b50;
+const ZIP_END_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP_END_SIZE: usize = 22;
+const ZIP_LOCAL_SIZE: usize = 30;
+const ZIP_CENTRAL_SIZE: usize = 46;
+
+/// Archive formats recognized by the pre-splitter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Tar,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Detect the archive format from the beginning of `data`
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if read_u32(data, 0) == Some(ZIP_LOCAL_SIGNATURE) {
+            Some(Self::Zip)
+        } else if tar_header(data).is_some() {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+
+    /// Offsets of the member boundaries of an archive, sorted, `0` and
+    /// `data.len()` excluded
+    ///
+    /// Parsing stops at the first malformed header, the end of the data is
+    /// then only chunked by content.
+    pub fn split_points(self, data: &[u8]) -> Vec<usize> {
+        let mut points = match self {
+            Self::Tar => tar_split_points(data),
+            Self::Zip => zip_split_points(data),
+        };
+        points.sort_unstable();
+        points.dedup();
+        points.retain(|point| *point > 0 && *point < data.len());
+        points
+    }
+}
+
+/// Member boundaries of `data` when it is a recognized archive
+pub fn split_points(data: &[u8]) -> Vec<usize> {
+    ArchiveFormat::detect(data)
+        .map(|format| format.split_points(data))
+        .unwrap_or_default()
+}
+
+/// Read a little endian integer of `N` bytes
+fn read_le<const N: usize>(data: &[u8], offset: usize) -> Option<u64> {
+    let bytes = data.get(offset..offset.checked_add(N)?)?;
+    Some(
+        bytes
+            .iter()
+            .rev()
+            .fold(0, |value, byte| value << 8 | *byte as u64),
+    )
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<usize> {
+    read_le::<2>(data, offset).map(|value| value as usize)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    read_le::<4>(data, offset).map(|value| value as u32)
+}
+
+/// Parse a tar numeric field, octal or GNU base-256
+fn tar_number(field: &[u8]) -> Option<u64> {
+    if field.first()? & 0x80 != 0 {
+        return Some(
+            field[field.len() - 8..]
+                .iter()
+                .fold(0, |value, byte| value << 8 | *byte as u64)
+                & (u64::MAX >> 1),
+        );
+    }
+    let digits: &[u8] = {
+        let start = field.iter().position(|b| *b != b' ')?;
+        let end = field[start..]
+            .iter()
+            .position(|b| *b == 0 || *b == b' ')
+            .map_or(field.len(), |end| start + end);
+        &field[start..end]
+    };
+    let digits = std::str::from_utf8(digits).ok()?;
+    u64::from_str_radix(digits, 8).ok()
+}
+
+/// Size of the member content when `data` starts with a valid tar header
+fn tar_header(data: &[u8]) -> Option<u64> {
+    let header = data.get(..TAR_BLOCK_SIZE)?;
+    let checksum = tar_number(&header[148..156])?;
+    let sum: u64 = header
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| {
+            if (148..156).contains(&i) {
+                b' ' as u64
+            } else {
+                *byte as u64
+            }
+        })
+        .sum();
+    if sum != checksum {
+        return None;
+    }
+    tar_number(&header[124..136])
+}
+
+fn tar_split_points(data: &[u8]) -> Vec<usize> {
+    let mut points = Vec::new();
+    let mut offset = 0;
+    while offset + TAR_BLOCK_SIZE <= data.len() {
+        let block = &data[offset..offset + TAR_BLOCK_SIZE];
+        if block.iter().all(|byte| *byte == 0) {
+            // end of archive
+            points.push(offset);
+            break;
+        }
+        let size = match tar_header(block) {
+            Some(size) => size as usize,
+            None => break,
+        };
+        let content = offset + TAR_BLOCK_SIZE;
+        points.push(offset);
+        points.push(content);
+
+        let blocks = (size + TAR_BLOCK_SIZE - 1) / TAR_BLOCK_SIZE;
+        offset = match blocks
+            .checked_mul(TAR_BLOCK_SIZE)
+            .and_then(|len| content.checked_add(len))
+        {
+            Some(offset) => offset,
+            None => break,
+        };
+    }
+    points
+}
+
+/// Offset of the content of the zip member whose local header is at `offset`
+fn zip_content(data: &[u8], offset: usize) -> Option<usize> {
+    if read_u32(data, offset)? != ZIP_LOCAL_SIGNATURE {
+        return None;
+    }
+    let name_len = read_u16(data, offset + 26)?;
+    let extra_len = read_u16(data, offset + 28)?;
+    Some(offset + ZIP_LOCAL_SIZE + name_len + extra_len)
+}
+
+fn zip_split_points(data: &[u8]) -> Vec<usize> {
+    // members are listed by the central directory, at the end of the archive
+    let end = (ZIP_END_SIZE..=data.len().min(ZIP_END_SIZE + u16::MAX as usize))
+        .map(|back| data.len() - back)
+        .find(|offset| read_u32(data, *offset) == Some(ZIP_END_SIGNATURE));
+    let mut points = Vec::new();
+    if let Some(end) = end {
+        let entries = read_u16(data, end + 10).unwrap_or(0);
+        let mut offset = read_u32(data, end + 16).unwrap_or(u32::MAX) as usize;
+        points.push(offset);
+        for _ in 0..entries {
+            if read_u32(data, offset) != Some(ZIP_CENTRAL_SIGNATURE) {
+                break;
+            }
+            if let Some(local) = read_u32(data, offset + 42).map(|local| local as usize) {
+                if let Some(content) = zip_content(data, local) {
+                    points.push(local);
+                    points.push(content);
+                }
+            }
+            let name_len = read_u16(data, offset + 28).unwrap_or(0);
+            let extra_len = read_u16(data, offset + 30).unwrap_or(0);
+            let comment_len = read_u16(data, offset + 32).unwrap_or(0);
+            offset += ZIP_CENTRAL_SIZE + name_len + extra_len + comment_len;
+        }
+        return points;
+    }
+
+    // truncated archive, walk the local headers while sizes are known
+    let mut offset = 0;
+    while let Some(content) = zip_content(data, offset) {
+        let flags = read_u16(data, offset + 6).unwrap_or(0);
+        let size = read_u32(data, offset + 18).unwrap_or(0) as usize;
+        points.push(offset);
+        points.push(content);
+        if flags & 0x08 != 0 {
+            // sizes are stored after the content
+            break;
+        }
+        offset = content + size;
+    }
+    points
+}
+
+/// Zero-copy iterator over the chunks of an archive, with a cut point at
+/// each member boundary
+///
+/// Data which isn't a recognized archive is chunked like
+/// [`SliceChunks`](crate::SliceChunks).
+#[derive(Debug, Clone)]
+pub struct ArchiveChunks<'a, A: ChunkingAlgorithm = FastCDC> {
+    data: &'a [u8],
+    cdc: A,
+    points: Vec<usize>, // forced cut points, ending with data length
+    next: usize,        // index of the next forced cut point
+    pos: usize,
+}
+
+impl<'a, A: ChunkingAlgorithm> ArchiveChunks<'a, A> {
+    pub fn new(data: &'a [u8], cdc: A) -> Self {
+        let mut points = split_points(data);
+        points.push(data.len());
+        Self {
+            data,
+            cdc,
+            points,
+            next: 0,
+            pos: 0,
+        }
+    }
+}
+
+impl<'a, A: ChunkingAlgorithm> Iterator for ArchiveChunks<'a, A> {
+    type Item = ChunkSlice<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.data.len() {
+            return None;
+        }
+
+        // every member is chunked as a whole content
+        let end = self.points[self.next];
+        let cut_pos = self.cdc.cut(&self.data[self.pos..end]);
+        let chunk = ChunkSlice {
+            offset: self.pos as u64,
+            data: &self.data[self.pos..self.pos + cut_pos],
+        };
+        self.pos += cut_pos;
+        if self.pos == end {
+            self.next += 1;
+        }
+        Some(chunk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake::Fake;
+    use std::collections::HashSet;
+    use std::io::{Cursor, Write};
+
+    fn random_data(len: usize) -> Vec<u8> {
+        (0..len).map(|_| (0..=255).fake::<u8>()).collect()
+    }
+
+    fn tar_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, name, *content).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn zip_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for (name, content) in files {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn chunk_set(data: &[u8]) -> HashSet<Vec<u8>> {
+        ArchiveChunks::new(data, FastCDC::default())
+            .map(|chunk| chunk.data.to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn tar_members() {
+        let (a, b) = (random_data(100_000), random_data(3_000));
+        let archive = tar_archive(&[("a", &a), ("b", &b)]);
+        assert_eq!(ArchiveFormat::detect(&archive), Some(ArchiveFormat::Tar));
+        let b_header = 512 + 196 * 512;
+        assert_eq!(
+            split_points(&archive),
+            [512, b_header, b_header + 512, b_header + 512 + 6 * 512]
+        );
+    }
+
+    #[test]
+    fn zip_members() {
+        let (a, b) = (random_data(100_000), random_data(3_000));
+        let archive = zip_archive(&[("a", &a), ("b", &b)]);
+        assert_eq!(ArchiveFormat::detect(&archive), Some(ArchiveFormat::Zip));
+        let points = split_points(&archive);
+        assert_eq!(points.len(), 4);
+        assert_eq!(&archive[points[0]..points[0] + a.len()], &a[..]);
+        assert_eq!(&archive[points[2]..points[2] + b.len()], &b[..]);
+
+        // without the central directory
+        let truncated = &archive[..points[2] + b.len()];
+        assert_eq!(split_points(truncated), points[..3]);
+    }
+
+    #[test]
+    fn shifted_members_dedup() {
+        let (a, b, c) = (random_data(200_000), random_data(50_000), random_data(777));
+        for (one, two) in [
+            (
+                tar_archive(&[("a", &a), ("b", &b)]),
+                tar_archive(&[("c", &c), ("a", &a)]),
+            ),
+            (
+                zip_archive(&[("a", &a), ("b", &b)]),
+                zip_archive(&[("c", &c), ("a", &a)]),
+            ),
+        ] {
+            let (one, two) = (chunk_set(&one), chunk_set(&two));
+            // member "a" gives the same chunks in both archives
+            let shared: usize = one.intersection(&two).map(|chunk| chunk.len()).sum();
+            assert!(shared >= a.len());
+        }
+
+        let data = random_data(100_000);
+        assert!(split_points(&data).is_empty());
+        let chunks: Vec<_> = ArchiveChunks::new(&data, FastCDC::default()).collect();
+        let expected: Vec<_> = FastCDC::default().slices(&data).collect();
+        assert_eq!(chunks, expected);
+    }
+}
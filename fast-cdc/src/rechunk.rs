@@ -0,0 +1,200 @@
+use crate::{Chunk, ChunkIter, ChunkingAlgorithm};
+use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult, Seek, SeekFrom};
+
+/// A modified byte range: `old_len` bytes at `offset` were replaced by
+/// `new_len` bytes.
+///
+/// Insertion has an `old_len` of 0 and deletion a `new_len` of 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edit {
+    pub offset: u64,
+    pub old_len: u64,
+    pub new_len: u64,
+}
+
+impl Edit {
+    pub fn new(offset: u64, old_len: u64, new_len: u64) -> Self {
+        Self {
+            offset,
+            old_len,
+            new_len,
+        }
+    }
+}
+
+/// A chunk of the new content
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkRef {
+    /// Old chunk `index` is unchanged, and now starts at `offset`
+    Reused { index: usize, offset: u64 },
+    /// Chunk found by re-chunking the edited region
+    New(Chunk),
+}
+
+impl ChunkRef {
+    /// Offset of the chunk in the new content
+    pub fn offset(&self) -> u64 {
+        match self {
+            Self::Reused { offset, .. } => *offset,
+            Self::New(chunk) => chunk.offset,
+        }
+    }
+}
+
+/// Re-chunk a content after an edit, see [`ChunkingAlgorithm::rechunk`]
+pub(crate) fn rechunk<A, R>(
+    cdc: &A,
+    mut src: R,
+    old: &[usize],
+    edit: Edit,
+) -> IoResult<Vec<ChunkRef>>
+where
+    A: ChunkingAlgorithm + Clone,
+    R: Read + Seek,
+{
+    // old chunk start offsets, with the old content length at the end
+    let mut starts = Vec::with_capacity(old.len() + 1);
+    let mut old_len = 0u64;
+    starts.push(old_len);
+    for len in old {
+        old_len += *len as u64;
+        starts.push(old_len);
+    }
+
+    let edit_end = edit.offset + edit.old_len;
+    if edit_end > old_len {
+        return Err(IoError::new(
+            ErrorKind::InvalidInput,
+            "edit is out of the old content",
+        ));
+    }
+    let new_len = old_len - edit.old_len + edit.new_len;
+    let new_edit_end = edit.offset + edit.new_len;
+    let max_size = cdc.config().max_size() as u64;
+
+    // 1. Reuse the chunks before the edit. A cut point depends on the
+    // first byte of the next chunk, and on the content length when the
+    // end of content is within the max size window.
+    let mut ret = Vec::with_capacity(old.len());
+    for (index, start) in starts[..old.len()].iter().enumerate() {
+        let end = starts[index + 1];
+        let eof_unchanged = old_len == new_len || start + max_size <= new_len.min(old_len);
+        if end >= edit.offset || !eof_unchanged {
+            break;
+        }
+        ret.push(ChunkRef::Reused {
+            index,
+            offset: *start,
+        });
+    }
+    let restart = starts[ret.len()];
+
+    // 2. Re-chunk from the last unaffected boundary until resync
+    src.seek(SeekFrom::Start(restart))?;
+    let mut offset = restart;
+    let mut chunks = ChunkIter::new(src, cdc.clone());
+    loop {
+        // is there an old chunk starting at the same content ?
+        if offset >= new_edit_end {
+            let old_offset = offset + edit.old_len - edit.new_len;
+            if let Ok(index) = starts[..old.len()].binary_search(&old_offset) {
+                ret.extend((index..old.len()).map(|index| ChunkRef::Reused {
+                    index,
+                    offset: starts[index] + edit.new_len - edit.old_len,
+                }));
+                break;
+            }
+        }
+
+        match chunks.next() {
+            Some(chunk) => {
+                let mut chunk = chunk?;
+                chunk.offset += restart;
+                offset = chunk.end_offset();
+                ret.push(ChunkRef::New(chunk));
+            }
+            None => break,
+        }
+    }
+
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChunkerConfig, FastCDC};
+    use fake::Fake;
+    use std::io::Cursor;
+
+    fn chunk_lens(cdc: &FastCDC, data: &[u8]) -> Vec<usize> {
+        cdc.slices(data).map(|chunk| chunk.len()).collect()
+    }
+
+    fn apply(data: &[u8], edit: Edit) -> Vec<u8> {
+        let start = edit.offset as usize;
+        let end = start + edit.old_len as usize;
+        let mut new_data = data[..start].to_vec();
+        new_data.extend((0..edit.new_len).map(|_| (0..=255).fake::<u8>()));
+        new_data.extend_from_slice(&data[end..]);
+        new_data
+    }
+
+    #[test]
+    fn rechunk_matches_full_chunking() {
+        let data: Vec<u8> = (0..1024 * 512).map(|_| (0..=255).fake::<u8>()).collect();
+        let cdc = FastCDC::new(ChunkerConfig::default());
+        let old = chunk_lens(&cdc, &data);
+
+        let len = data.len() as u64;
+        let edits = [
+            Edit::new(0, 0, 10),
+            Edit::new(0, 100, 0),
+            Edit::new(200_000, 0, 1),
+            Edit::new(200_000, 1, 0),
+            Edit::new(200_000, 50_000, 3_000),
+            Edit::new(300_000, 10, 10),
+            Edit::new(len - 100, 100, 0),
+            Edit::new(len, 0, 70_000),
+            Edit::new(0, len, 1000),
+        ];
+        for edit in edits {
+            let new_data = apply(&data, edit);
+            let ret = cdc.rechunk(Cursor::new(&new_data), &old, edit).unwrap();
+
+            let mut offset = 0;
+            let mut lens = Vec::new();
+            for chunk in &ret {
+                assert_eq!(chunk.offset(), offset);
+                let len = match chunk {
+                    ChunkRef::Reused { index, .. } => old[*index],
+                    ChunkRef::New(chunk) => {
+                        let start = chunk.offset as usize;
+                        assert_eq!(&new_data[start..start + chunk.len()], &chunk.data[..]);
+                        chunk.len()
+                    }
+                };
+                offset += len as u64;
+                lens.push(len);
+            }
+            assert_eq!(lens, chunk_lens(&cdc, &new_data), "{:?}", edit);
+        }
+
+        // an edit in the middle only rewrites a few chunks
+        let edit = Edit::new(200_000, 1, 1);
+        let new_data = apply(&data, edit);
+        let ret = cdc.rechunk(Cursor::new(&new_data), &old, edit).unwrap();
+        let new_chunks = ret
+            .iter()
+            .filter(|chunk| matches!(chunk, ChunkRef::New(_)))
+            .count();
+        assert!(new_chunks <= 3);
+    }
+
+    #[test]
+    fn edit_out_of_content() {
+        let cdc = FastCDC::default();
+        let ret = cdc.rechunk(Cursor::new(vec![0u8; 10]), &[10], Edit::new(5, 10, 0));
+        assert!(ret.is_err());
+    }
+}
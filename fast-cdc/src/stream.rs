@@ -0,0 +1,110 @@
+use crate::iter::ChunkBuffer;
+use crate::{Chunk, ChunkingAlgorithm, FastCDC};
+use futures_core::Stream;
+use std::io::Result as IoResult;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+/// Stream of the chunks of an [`AsyncRead`] source
+///
+/// The async version of [`ChunkIter`](crate::ChunkIter), chunks are the same.
+///
+/// ```
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// use fast_cdc::{ChunkStream, FastCDC};
+/// use std::future::poll_fn;
+/// use std::pin::Pin;
+/// use futures_core::Stream;
+///
+/// let data = vec![42u8; 100 * 1024];
+/// let mut chunks = ChunkStream::new(&data[..], FastCDC::default());
+/// let mut len = 0;
+/// while let Some(chunk) = poll_fn(|cx| Pin::new(&mut chunks).poll_next(cx)).await {
+///     len += chunk.unwrap().len();
+/// }
+/// assert_eq!(len, data.len());
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ChunkStream<R: AsyncRead + Unpin, A: ChunkingAlgorithm = FastCDC> {
+    src: R,              // source reader
+    buf: ChunkBuffer<A>, // read buffer
+}
+
+impl<R: AsyncRead + Unpin, A: ChunkingAlgorithm> ChunkStream<R, A> {
+    pub fn new(src: R, cdc: A) -> Self {
+        Self {
+            src,
+            buf: ChunkBuffer::new(cdc),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.src
+    }
+}
+
+impl<R: AsyncRead + Unpin, A: ChunkingAlgorithm + Unpin> Stream for ChunkStream<R, A> {
+    type Item = IoResult<Chunk>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        // read source until a whole max size window is buffered, or end of file
+        while this.buf.needs_data() {
+            let mut read_buf = ReadBuf::new(this.buf.spare());
+            match Pin::new(&mut this.src).poll_read(cx, &mut read_buf) {
+                Poll::Ready(Ok(())) => {
+                    let read = read_buf.filled().len();
+                    this.buf.advance(read);
+                }
+                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err))),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        Poll::Ready(this.buf.next_chunk().map(Ok))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChunkerConfig, ChunkingAlgorithm};
+    use fake::Fake;
+    use std::future::poll_fn;
+    use tokio::io::AsyncWriteExt;
+
+    async fn collect<S: Stream<Item = IoResult<Chunk>> + Unpin>(mut stream: S) -> Vec<Chunk> {
+        let mut chunks = Vec::new();
+        while let Some(chunk) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            chunks.push(chunk.unwrap());
+        }
+        chunks
+    }
+
+    #[tokio::test]
+    async fn stream_matches_slice() {
+        let data: Vec<u8> = (0..1024 * 300).map(|_| (0..=255).fake::<u8>()).collect();
+        let cdc = FastCDC::new(ChunkerConfig::default());
+        let expected: Vec<Chunk> = cdc.slices(&data).map(|chunk| chunk.to_chunk()).collect();
+
+        assert_eq!(
+            collect(ChunkStream::new(&data[..], cdc.clone())).await,
+            expected
+        );
+
+        // source written in small pieces by another task
+        let (mut writer, reader) = tokio::io::duplex(1000);
+        let input = data.clone();
+        let write = tokio::spawn(async move {
+            for part in input.chunks(777) {
+                writer.write_all(part).await.unwrap();
+            }
+        });
+        assert_eq!(collect(ChunkStream::new(reader, cdc)).await, expected);
+        write.await.unwrap();
+    }
+}
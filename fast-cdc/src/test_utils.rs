@@ -0,0 +1,30 @@
+//! Helpers shared by the unit tests
+use std::io::{Error as IoError, ErrorKind, Result as IoResult, Seek, SeekFrom, Write};
+
+/// Record length of chunks written by a Chunker
+#[derive(Debug, Default)]
+pub(crate) struct ChunkLens(pub(crate) Vec<usize>);
+
+impl Write for ChunkLens {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        self.0.push(buf.len());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        Ok(())
+    }
+}
+
+impl Seek for ChunkLens {
+    /// Chunks are only appended, the position is the end of the last one
+    fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
+        match pos {
+            SeekFrom::Current(0) | SeekFrom::End(0) => Ok(self.0.iter().sum::<usize>() as u64),
+            _ => Err(IoError::new(
+                ErrorKind::Unsupported,
+                "chunk lengths are append only",
+            )),
+        }
+    }
+}
@@ -1,22 +1,51 @@
-extern crate cdchunking;
 extern crate fast_cdc;
 
-use cdchunking::Chunker;
-use fast_cdc::FastCDC;
-use std::fs::File;
-use std::io::prelude::*;
+use fake::Fake;
+use fast_cdc::analysis::DedupAnalyzer;
+use fast_cdc::{Algorithm, ChunkerConfig};
+use std::fs;
 
 #[test]
 fn main() -> Result<(), std::io::Error> {
-    let chunker = Chunker::new(FastCDC {});
+    // 1. Create a directory with duplicated content
+    let dir = std::env::temp_dir().join(format!("fast-cdc-analysis-{}", std::process::id()));
+    fs::create_dir_all(dir.join("sub"))?;
+    let data: Vec<u8> = (0..1024 * 256).map(|_| (0..=255).fake::<u8>()).collect();
+    let mut edited = data.clone();
+    edited.splice(100_000..100_000, b"hello".iter().copied());
+    fs::write(dir.join("a.bin"), &data)?;
+    fs::write(dir.join("sub/b.bin"), &data)?;
+    fs::write(dir.join("sub/c.bin"), &edited)?;
 
-    let mut file = File::open("./sandbox/bin/cp")?;
-    let mut data = Vec::new();
-    file.read_to_end(&mut data)?;
+    // 2. Analyze it with every algorithm
+    for algorithm in [
+        Algorithm::FastCDC2016,
+        Algorithm::FastCDC2020,
+        Algorithm::Rabin,
+        Algorithm::Buzhash,
+    ] {
+        let mut analyzer = DedupAnalyzer::new(algorithm.build(ChunkerConfig::default()));
+        analyzer.add_dir(&dir)?;
+        let report = analyzer.into_report();
+        assert!(report.unique_bytes < report.total_bytes);
+        assert!(report.unique_chunks < report.chunks);
 
-    for slice in chunker.slices(&data) {
-        println!("chunk len {:?}", slice.len());
+        let paths: Vec<_> = report.files.iter().map(|file| file.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                dir.join("a.bin"),
+                dir.join("sub/b.bin"),
+                dir.join("sub/c.bin")
+            ]
+        );
+        assert_eq!(report.total_bytes, (data.len() * 2 + edited.len()) as u64);
+        assert_eq!(report.files[0].unique_bytes, data.len() as u64);
+        assert_eq!(report.files[1].unique_bytes, 0);
+        // only the chunks around the insertion are new
+        assert!(report.files[2].dedup_ratio() > 0.8);
+        assert_eq!(report.histogram.values().sum::<u64>(), report.chunks);
     }
 
-    Ok(())
+    fs::remove_dir_all(&dir)
 }
@@ -1,51 +1,231 @@
-/// Block address
-#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+use crate::encoding::{get_bytes, get_varint, put_varint};
+use crate::error::{Error, Result};
+use crate::multihash::{MultiHash, MultiHashCode};
+use crate::BlockId;
+use multibase::Base;
+use serde::de::{Deserializer, Error as DeError};
+use serde::ser::Serializer;
+use std::convert::TryFrom;
+use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+/// Max digest size of a block address, the output size of the hash functions
+pub const MAX_DIGEST_LEN: usize = 32;
+
+/// Address of a stored block, a multihash of fixed size
+///
+/// Content addressed blocks use the multihash of their content, blocks
+/// updated in place (nodes, contents, trees) the identity multihash of
+/// their [`BlockId`].
+///
+/// The string form is the multibase encoded multihash, base32 lower case.
+/// Binary serializers store the multihash bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct BlockAddress {
-    address: String,
-    len: usize,
-    offset: usize,
+    code: MultiHashCode,
+    len: u8,
+    digest: [u8; MAX_DIGEST_LEN],
 }
 
 impl BlockAddress {
-    pub fn new(address: String, len: usize, offset: usize) -> Self {
-        Self {
-            address,
-            len,
-            offset,
+    /// Address of the content embedded in its parent, the empty identity
+    /// multihash, never stored
+    pub const INLINE: BlockAddress = BlockAddress {
+        code: MultiHashCode::Identity,
+        len: 0,
+        digest: [0; MAX_DIGEST_LEN],
+    };
+
+    /// Address of a digest, `None` when it is longer than [`MAX_DIGEST_LEN`]
+    pub fn new(code: MultiHashCode, digest: &[u8]) -> Option<Self> {
+        if digest.len() > MAX_DIGEST_LEN {
+            return None;
         }
+        let mut address = Self {
+            code,
+            len: digest.len() as u8,
+            digest: [0; MAX_DIGEST_LEN],
+        };
+        address.digest[..digest.len()].copy_from_slice(digest);
+        Some(address)
     }
 
+    /// Hash function of the address
     #[inline]
-    pub fn get_address(&self) -> String {
-        self.address.clone()
+    pub fn code(&self) -> MultiHashCode {
+        self.code
     }
 
     #[inline]
-    pub fn set_offset(&mut self, offset: usize) {
-        self.offset = offset;
+    pub fn digest(&self) -> &[u8] {
+        &self.digest[..self.len as usize]
     }
 
-    #[inline]
-    pub fn set_len(&mut self, len: usize) {
-        self.len = len;
+    /// Binary form of the address: hash code, digest size and digest
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(4 + self.len as usize);
+        put_varint(&mut buf, u64::from(self.code));
+        put_varint(&mut buf, self.len as u64);
+        buf.extend_from_slice(self.digest());
+        buf
     }
 
-    #[inline]
-    pub fn len(&self) -> usize {
-        self.len
+    /// Parse the binary form of an address
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let (code, buf) = get_varint(data)?;
+        let code =
+            MultiHashCode::try_from(code).map_err(|_| Error::UnsupportedHash(code as u32))?;
+        let (digest, buf) = get_bytes(buf)?;
+        if !buf.is_empty() {
+            return Err(Error::InvalidAddress("trailing bytes"));
+        }
+        Self::new(code, digest).ok_or(Error::InvalidAddress("digest too long"))
     }
-    #[inline]
-    pub fn offset(&self) -> usize {
-        self.offset
+}
+
+impl TryFrom<&MultiHash> for BlockAddress {
+    type Error = Error;
+
+    fn try_from(mh: &MultiHash) -> Result<Self> {
+        Self::new(mh.algorithm()?, mh.digest()).ok_or(Error::InvalidAddress("digest too long"))
     }
+}
 
-    #[inline]
-    pub fn is_empty(&self) -> bool {
-        self.len() == 0
+impl From<BlockId> for BlockAddress {
+    fn from(id: BlockId) -> Self {
+        Self::new(MultiHashCode::Identity, id.as_bytes()).expect("block id fits in an address")
     }
+}
 
-    #[inline]
-    pub fn end_offset(&self) -> usize {
-        self.offset() + self.len()
+impl Display for BlockAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(&multibase::encode(Base::Base32Lower, self.to_bytes()))
+    }
+}
+
+impl Debug for BlockAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "BlockAddress({})", self)
+    }
+}
+
+impl FromStr for BlockAddress {
+    type Err = Error;
+
+    /// Parse a multibase encoded address, whatever its base
+    fn from_str(s: &str) -> Result<Self> {
+        let (_, data) =
+            multibase::decode(s).map_err(|_| Error::InvalidAddress("invalid multibase"))?;
+        Self::from_bytes(&data)
+    }
+}
+
+impl BlockAddress {
+    /// Name of the address in storages written before multibase addresses
+    ///
+    /// Blocks updated in place were named by the string form of their
+    /// [`BlockId`], content addressed blocks by their base58 encoded BLAKE3
+    /// digest. `None` for the other hash functions, which weren't supported.
+    pub fn legacy_name(&self) -> Option<String> {
+        match self.code {
+            MultiHashCode::Identity => BlockId::from_slice(self.digest()).map(|id| id.to_string()),
+            MultiHashCode::Blake3 => Some(multibase::encode(Base::Base58Btc, self.digest())),
+            _ => None,
+        }
+    }
+
+    /// Parse a legacy name, see [`legacy_name`]
+    ///
+    /// [`legacy_name`]: BlockAddress::legacy_name
+    pub fn from_legacy_name(name: &str) -> Result<Self> {
+        if let Ok(id) = name.parse::<BlockId>() {
+            return Ok(id.into());
+        }
+        match multibase::decode(name) {
+            Ok((Base::Base58Btc, digest)) => Self::new(MultiHashCode::Blake3, &digest)
+                .ok_or(Error::InvalidAddress("digest too long")),
+            _ => Err(Error::InvalidAddress("invalid legacy name")),
+        }
+    }
+}
+
+impl serde::Serialize for BlockAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_bytes(&self.to_bytes())
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for BlockAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            s.parse().map_err(DeError::custom)
+        } else {
+            let bytes = serde_bytes::ByteBuf::deserialize(deserializer)?;
+            Self::from_bytes(&bytes).map_err(DeError::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mh = MultiHashCode::Blake3.digest(b"shelter").unwrap();
+        let address = BlockAddress::try_from(&mh).unwrap();
+        assert_eq!(address.code(), MultiHashCode::Blake3);
+        assert_eq!(address.digest(), mh.digest());
+        assert_eq!(address.to_bytes()[..2], [0x1e, 0x20]);
+
+        let string = address.to_string();
+        assert!(string.starts_with('b'));
+        assert_eq!(string.parse::<BlockAddress>().unwrap(), address);
+        assert!("not an address".parse::<BlockAddress>().is_err());
+
+        // compact binary serialization, a length prefix and the multihash
+        let bytes = bincode::serialize(&address).unwrap();
+        assert_eq!(bytes.len(), 8 + 34);
+        assert_eq!(
+            bincode::deserialize::<BlockAddress>(&bytes).unwrap(),
+            address
+        );
+    }
+
+    #[test]
+    fn block_id() {
+        let id = BlockId::new();
+        let address = BlockAddress::from(id);
+        assert_eq!(address.code(), MultiHashCode::Identity);
+        assert_eq!(address.digest(), id.as_bytes());
+        assert_ne!(address, BlockAddress::from(BlockId::new()));
+        assert!(BlockAddress::new(MultiHashCode::Identity, &[0; MAX_DIGEST_LEN + 1]).is_none());
+    }
+
+    #[test]
+    fn legacy_name() {
+        let id = BlockId::new();
+        assert_eq!(BlockAddress::from(id).legacy_name(), Some(id.to_string()));
+
+        let mh = MultiHashCode::Blake3.digest(b"shelter").unwrap();
+        let address = BlockAddress::try_from(&mh).unwrap();
+        let name = address.legacy_name().unwrap();
+        assert!(name.starts_with('z'));
+        assert_eq!(multibase::decode(&name).unwrap().1, mh.digest());
+        assert_eq!(BlockAddress::from_legacy_name(&name).unwrap(), address);
+        assert_eq!(
+            BlockAddress::from_legacy_name(&id.to_string()).unwrap(),
+            BlockAddress::from(id)
+        );
+        assert!(BlockAddress::from_legacy_name(&address.to_string()).is_err());
+
+        let mh = MultiHashCode::Sha2_256.digest(b"shelter").unwrap();
+        assert_eq!(BlockAddress::try_from(&mh).unwrap().legacy_name(), None);
+        assert_eq!(BlockAddress::INLINE.legacy_name(), None);
     }
 }
@@ -1,11 +1,14 @@
-// use std::mem::size_of;
+use crate::error::{Error, Result};
 use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+use std::time::SystemTime;
 use xid::{new, Id};
 
-/// BlockId use SonyFlake to generate id with the following properties :
-/// 39 bits for time in units of 10 msec
-/// 8 bits for a sequence number
-/// 16 bits for a machine id
+// BlockId use xid to generate id with the following layout :
+// 4 bytes for time in seconds since the epoch (big endian)
+// 3 bytes for a machine id
+// 2 bytes for a process id
+// 3 bytes for a counter, starting at a random value
 
 const RAW_LEN: usize = 12;
 
@@ -13,6 +16,10 @@ const RAW_LEN: usize = 12;
 #[serde(remote = "Id")]
 pub struct IdDef(pub [u8; RAW_LEN]);
 
+/// Unique id of a block updated in place
+///
+/// Ids are ordered by creation time, to the second, then by machine,
+/// process and counter. The string form is the 20 characters xid encoding.
 #[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Hash, Clone, Copy, Deserialize, Serialize)]
 pub struct BlockId(#[serde(with = "IdDef")] Id);
 
@@ -21,6 +28,19 @@ impl BlockId {
         BlockId(new())
     }
 
+    /// Create a new id whose machine id is derived from the actor id of a
+    /// repository replica instead of the host
+    ///
+    /// Ids created by the same actor share a machine component derived from
+    /// the actor, whatever the host. The time, process id and counter still
+    /// change on every call, ids aren't deterministic.
+    pub fn with_actor(actor: &str) -> Self {
+        let mut raw = *new().as_bytes();
+        // machine id bytes, following the time
+        raw[4..7].copy_from_slice(&blake3::hash(actor.as_bytes()).as_bytes()[..3]);
+        BlockId(Id(raw))
+    }
+
     /// Always return 42 Id value
     pub const fn get_magic() -> Self {
         let mut array: [u8; RAW_LEN] = [0; RAW_LEN];
@@ -28,12 +48,51 @@ impl BlockId {
         BlockId(Id(array))
     }
 
-    // pub fn from_slice(buf: &[u8]) -> Self {
-    //     assert_eq!(buf.len(), size_of::<BlockId>());
-    //     let id = BlockId::default();
-    //     id.0.copy_from_slice(buf);
-    //     id
-    // }
+    pub const fn from_bytes(bytes: [u8; RAW_LEN]) -> Self {
+        BlockId(Id(bytes))
+    }
+
+    /// Id from its raw bytes, `None` when the slice isn't 12 bytes long
+    pub fn from_slice(buf: &[u8]) -> Option<Self> {
+        if buf.len() != RAW_LEN {
+            return None;
+        }
+        let mut bytes = [0; RAW_LEN];
+        bytes.copy_from_slice(buf);
+        Some(Self::from_bytes(bytes))
+    }
+
+    /// Raw bytes of the id
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; RAW_LEN] {
+        self.0.as_bytes()
+    }
+
+    /// Creation time of the id, to the second
+    #[inline]
+    pub fn created_at(&self) -> SystemTime {
+        self.0.time()
+    }
+
+    /// Machine id, of the host or of the actor, see [`with_actor`]
+    ///
+    /// [`with_actor`]: BlockId::with_actor
+    #[inline]
+    pub fn machine(&self) -> [u8; 3] {
+        self.0.machine()
+    }
+
+    /// Id of the process which created the id
+    #[inline]
+    pub fn pid(&self) -> u16 {
+        self.0.pid()
+    }
+
+    /// Counter of the ids created by the process
+    #[inline]
+    pub fn counter(&self) -> u32 {
+        self.0.counter()
+    }
 }
 
 impl Default for BlockId {
@@ -48,13 +107,44 @@ impl Display for BlockId {
     }
 }
 
+impl FromStr for BlockId {
+    type Err = Error;
+
+    /// Parse the string form of an id
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(BlockId(s.parse()?))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::BlockId;
+    use std::time::{Duration, SystemTime};
 
     #[test]
     fn test1() {
         let id = BlockId::new();
         println!("{:?}", id.to_string());
     }
+
+    #[test]
+    fn parse_and_parts() {
+        let id = BlockId::new();
+        assert_eq!(id.to_string().parse::<BlockId>().unwrap(), id);
+        assert!("not an id".parse::<BlockId>().is_err());
+        assert!("zzzzzzzzzzzzzzzzzzzz".parse::<BlockId>().is_err());
+        assert_eq!(BlockId::from_slice(id.as_bytes()), Some(id));
+        assert_eq!(BlockId::from_slice(&id.as_bytes()[1..]), None);
+
+        let elapsed = SystemTime::now().duration_since(id.created_at()).unwrap();
+        assert!(elapsed < Duration::from_secs(2));
+        let next = BlockId::new();
+        assert!(next > id);
+        assert_eq!((next.machine(), next.pid()), (id.machine(), id.pid()));
+
+        let actor = BlockId::with_actor("42");
+        assert_eq!(actor.machine(), BlockId::with_actor("42").machine());
+        assert_ne!(actor.machine(), BlockId::with_actor("43").machine());
+        assert_eq!(actor.pid(), id.pid());
+    }
 }
@@ -0,0 +1,82 @@
+use crate::BlockAddress;
+
+/// Reference to a chunk of file content stored in a block
+///
+/// Small chunks are inlined: the reference keeps the chunk in a side field
+/// instead of a stored block, and its address is the sentinel
+/// [`BlockAddress::INLINE`], which addresses no block.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BlockRef {
+    address: BlockAddress,
+    len: usize,
+    offset: usize,
+    #[serde(with = "serde_bytes")]
+    inline: Option<Vec<u8>>,
+}
+
+impl BlockRef {
+    pub fn new(address: BlockAddress, len: usize, offset: usize) -> Self {
+        Self {
+            address,
+            len,
+            offset,
+            inline: None,
+        }
+    }
+
+    /// Reference embedding a chunk, addressed by [`BlockAddress::INLINE`]
+    pub fn inline(data: Vec<u8>, offset: usize) -> Self {
+        Self {
+            address: BlockAddress::INLINE,
+            len: data.len(),
+            offset,
+            inline: Some(data),
+        }
+    }
+
+    #[inline]
+    pub fn get_address(&self) -> BlockAddress {
+        self.address
+    }
+
+    /// Embedded chunk of an inline reference
+    #[inline]
+    pub fn inline_data(&self) -> Option<&[u8]> {
+        self.inline.as_deref()
+    }
+
+    /// Whether the chunk is embedded instead of stored in a block
+    #[inline]
+    pub fn is_inline(&self) -> bool {
+        self.inline.is_some()
+    }
+
+    #[inline]
+    pub fn set_offset(&mut self, offset: usize) {
+        self.offset = offset;
+    }
+
+    #[inline]
+    pub fn set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    pub fn end_offset(&self) -> usize {
+        self.offset() + self.len()
+    }
+}
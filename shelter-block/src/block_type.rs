@@ -1,35 +1,57 @@
 use std::convert::TryFrom;
 
 /// Different type of shelter blocks
-// TODO: put in private mode (Range: 0x300000 – 0x3FFFFF)
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+///
+/// Codes are multicodecs of the private use range (0x300000 – 0x3FFFFF).
+/// Blocks written before used the codes 0x31 – 0x36, see [`from_legacy_code`].
+///
+/// [`from_legacy_code`]: BlockType::from_legacy_code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum BlockType {
-    SBLK = 0x31, // Super block
-    BLOB = 0x32,
-    FILE = 0x33,
-    TREE = 0x34,
-    FVER = 0x35, // File version
-    INDX = 0x36, // Index
+    SBLK = 0x300031, // Super block
+    BLOB = 0x300032,
+    FILE = 0x300033,
+    TREE = 0x300034,
+    FVER = 0x300035, // File version
+    INDX = 0x300036, // Index
 }
 
-impl TryFrom<u8> for BlockType {
+/// Offset of the private codes from the legacy ones
+const PRIVATE_RANGE: u64 = 0x300000;
+
+impl BlockType {
+    /// Type of a block written with the legacy codes, in the public range
+    pub fn from_legacy_code(code: u64) -> Option<Self> {
+        match code {
+            0x31..=0x36 => Self::try_from(PRIVATE_RANGE + code).ok(),
+            _ => None,
+        }
+    }
+
+    /// Legacy code of the type, in the public range
+    pub fn legacy_code(self) -> u64 {
+        u64::from(self) - PRIVATE_RANGE
+    }
+}
+
+impl TryFrom<u64> for BlockType {
     type Error = String;
 
-    fn try_from(raw: u8) -> Result<Self, Self::Error> {
+    fn try_from(raw: u64) -> Result<Self, Self::Error> {
         match raw {
-            0x31 => Ok(Self::SBLK),
-            0x32 => Ok(Self::BLOB),
-            0x33 => Ok(Self::FILE),
-            0x34 => Ok(Self::TREE),
-            0x35 => Ok(Self::FVER),
-            0x36 => Ok(Self::INDX),
+            0x300031 => Ok(Self::SBLK),
+            0x300032 => Ok(Self::BLOB),
+            0x300033 => Ok(Self::FILE),
+            0x300034 => Ok(Self::TREE),
+            0x300035 => Ok(Self::FVER),
+            0x300036 => Ok(Self::INDX),
             _ => Err("invalid code".to_string()),
         }
     }
 }
 
-impl From<BlockType> for u8 {
+impl From<BlockType> for u64 {
     fn from(code: BlockType) -> Self {
-        code as u8
+        code as u64
     }
 }
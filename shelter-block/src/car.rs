@@ -0,0 +1,493 @@
+//! CAR v1 (Content Addressable aRchive) import and export
+//!
+//! <header size><header><section>*
+//!   - header: DAG-CBOR map `{ "roots": [CID], "version": 1 }`, and
+//!     `"schemas": { type code: schema version }` when a type has a schema
+//!     version other than 0
+//!   - section: <size (varint)><CID><block content>
+//!
+//! Blocks are stored as their CID and uncompressed content, the block type
+//! and the multihash being part of the CID. Archives are read and written one block
+//! at a time, so they can exceed memory.
+//!
+//! Signatures are not preserved: a section has no room for the signer, so
+//! imported blocks are unsigned, whatever the exported blocks were.
+use crate::encoding::{get_varint, put_varint};
+use crate::error::{Error, Result};
+use crate::{AddressKey, Block, BlockType, Cid, Compression, SIGNATURE};
+use std::convert::TryFrom;
+use std::io::{ErrorKind, Read, Write};
+use unsigned_varint::decode;
+
+const CAR_VERSION: u64 = 1;
+
+/// Max size of the archive header, a corrupted size isn't allocated
+const MAX_HEADER_SIZE: u64 = 1024 * 1024;
+
+/// Max size of a section, larger than any block (chunks are at most 16 MiB)
+const MAX_SECTION_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Schema versions of the block types whose schema isn't 0
+type Schemas = Vec<(BlockType, u32)>;
+
+/// CBOR major types
+const CBOR_UINT: u8 = 0;
+const CBOR_BYTES: u8 = 2;
+const CBOR_TEXT: u8 = 3;
+const CBOR_ARRAY: u8 = 4;
+const CBOR_MAP: u8 = 5;
+const CBOR_TAG: u8 = 6;
+
+/// CBOR tag of a CID in DAG-CBOR
+const CID_TAG: u64 = 42;
+
+/// Write blocks to a CAR archive
+///
+/// The signer of a block isn't written, see [`CarReader`].
+#[derive(Debug)]
+pub struct CarWriter<W: Write> {
+    writer: W,
+    schemas: Schemas,
+}
+
+impl<W: Write> CarWriter<W> {
+    /// Start an archive of the blocks reachable from `roots`, every block
+    /// having schema 0
+    pub fn new(writer: W, roots: &[Cid]) -> Result<Self> {
+        Self::with_schemas(writer, roots, &[])
+    }
+
+    /// Start an archive of the blocks reachable from `roots`, with the
+    /// schema version of the block types whose schema isn't 0
+    ///
+    /// The schemas are recorded in the header, a block of another schema
+    /// version is rejected by [`write_block`].
+    ///
+    /// [`write_block`]: CarWriter::write_block
+    pub fn with_schemas(
+        mut writer: W,
+        roots: &[Cid],
+        schemas: &[(BlockType, u32)],
+    ) -> Result<Self> {
+        let schemas: Schemas = schemas
+            .iter()
+            .copied()
+            .filter(|(_, schema)| *schema != 0)
+            .collect();
+        let header = encode_header(roots, &schemas);
+        let mut buf = Vec::with_capacity(header.len() + 4);
+        put_varint(&mut buf, header.len() as u64);
+        buf.extend_from_slice(&header);
+        writer.write_all(&buf)?;
+        Ok(Self { writer, schemas })
+    }
+
+    /// Append a block to the archive, decompressing its content and dropping
+    /// its signer
+    pub fn write_block(&mut self, block: &Block) -> Result<()> {
+        if block.schema != schema_of(&self.schemas, block.block_type) {
+            return Err(Error::InvalidCar("block schema not declared in the header"));
+        }
+        let cid = block.cid().to_bytes();
+        let data = block.try_get_data()?;
+        let mut buf = Vec::with_capacity(cid.len() + 10);
+        put_varint(&mut buf, (cid.len() + data.len()) as u64);
+        buf.extend_from_slice(&cid);
+        self.writer.write_all(&buf)?;
+        self.writer.write_all(&data)?;
+        Ok(())
+    }
+
+    /// Flush the archive and return the underlying writer
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Read the blocks of a CAR archive
+///
+/// Every block is verified against its CID, iteration stops at the first
+/// error. Blocks with a keyed address need the key, see [`set_key`].
+///
+/// Archives carry no signature, every block read is unsigned: its
+/// authenticity is the one of the archive source. Loading them with a list
+/// of trusted devices fails with [`Error::Unsigned`], they must be signed
+/// again by a trusted device once the archive is trusted.
+///
+/// [`set_key`]: CarReader::set_key
+#[derive(Debug)]
+pub struct CarReader<R: Read> {
+    reader: R,
+    roots: Vec<Cid>,
+    schemas: Schemas,
+    key: Option<AddressKey>, // verifies keyed addresses
+    failed: bool,
+}
+
+impl<R: Read> CarReader<R> {
+    /// Open an archive, reading its header
+    pub fn new(mut reader: R) -> Result<Self> {
+        let size = read_varint(&mut reader)?.ok_or(Error::InvalidCar("empty archive"))?;
+        if size > MAX_HEADER_SIZE {
+            return Err(Error::InvalidCar("header too large"));
+        }
+        let header = read_exact(&mut reader, size)?;
+        let (roots, schemas) = decode_header(&header)?;
+        Ok(Self {
+            reader,
+            roots,
+            schemas,
+            key: None,
+            failed: false,
+        })
+    }
+
+    /// Set the key used to verify blocks with a keyed address
+    #[inline]
+    pub fn set_key(&mut self, key: Option<AddressKey>) {
+        self.key = key;
+    }
+
+    /// Roots of the archive
+    #[inline]
+    pub fn roots(&self) -> &[Cid] {
+        &self.roots
+    }
+
+    /// Read the next block, `None` at the end of the archive
+    pub fn next_block(&mut self) -> Result<Option<Block>> {
+        let size = match read_varint(&mut self.reader)? {
+            Some(size) => size,
+            None => return Ok(None),
+        };
+        if size > MAX_SECTION_SIZE {
+            return Err(Error::InvalidCar("section too large"));
+        }
+        let section = read_exact(&mut self.reader, size)?;
+        let (cid, data) = Cid::read_bytes(&section)?;
+        let block = Block {
+            signature: SIGNATURE,
+            compression: Compression::Identity,
+            mh: cid.hash().clone(),
+            block_type: cid.codec(),
+            schema: schema_of(&self.schemas, cid.codec()),
+            data: data.to_vec(),
+            signer: None,
+        };
+        block.verify_with_key(self.key.as_ref())?;
+        Ok(Some(block))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Iterator for CarReader<R> {
+    type Item = Result<Block>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let next = self.next_block();
+        self.failed = next.is_err();
+        next.transpose()
+    }
+}
+
+/// Schema version of a block type, 0 when not listed
+fn schema_of(schemas: &[(BlockType, u32)], block_type: BlockType) -> u32 {
+    schemas
+        .iter()
+        .find(|(listed, _)| *listed == block_type)
+        .map_or(0, |(_, schema)| *schema)
+}
+
+/// Read a varint, `None` at the end of the stream
+fn read_varint<R: Read>(reader: &mut R) -> Result<Option<u64>> {
+    let mut buf = [0u8; 10];
+    for i in 0..buf.len() {
+        match reader.read_exact(&mut buf[i..=i]) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof && i == 0 => return Ok(None),
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                return Err(Error::InvalidCar("truncated"))
+            }
+            Err(err) => return Err(err.into()),
+        }
+        if decode::is_last(buf[i]) {
+            return get_varint(&buf[..=i]).map(|(value, _)| Some(value));
+        }
+    }
+    Err(Error::Malformed("invalid varint"))
+}
+
+fn read_exact<R: Read>(reader: &mut R, size: u64) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    reader.take(size).read_to_end(&mut buf)?;
+    if (buf.len() as u64) < size {
+        return Err(Error::InvalidCar("truncated"));
+    }
+    Ok(buf)
+}
+
+fn put_cbor_head(buf: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    if value < 24 {
+        buf.push(major | value as u8);
+    } else if value <= u8::MAX as u64 {
+        buf.push(major | 24);
+        buf.push(value as u8);
+    } else if value <= u16::MAX as u64 {
+        buf.push(major | 25);
+        buf.extend_from_slice(&(value as u16).to_be_bytes());
+    } else if value <= u32::MAX as u64 {
+        buf.push(major | 26);
+        buf.extend_from_slice(&(value as u32).to_be_bytes());
+    } else {
+        buf.push(major | 27);
+        buf.extend_from_slice(&value.to_be_bytes());
+    }
+}
+
+fn put_cbor_text(buf: &mut Vec<u8>, text: &str) {
+    put_cbor_head(buf, CBOR_TEXT, text.len() as u64);
+    buf.extend_from_slice(text.as_bytes());
+}
+
+/// Read a CBOR item head, returns its major type and argument
+fn get_cbor_head(buf: &[u8]) -> Result<(u8, u64, &[u8])> {
+    let (first, buf) = buf.split_first().ok_or(Error::InvalidCar("truncated"))?;
+    let major = first >> 5;
+    let size = match first & 0x1f {
+        info @ 0..=23 => return Ok((major, info as u64, buf)),
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        _ => return Err(Error::InvalidCar("unsupported CBOR item")),
+    };
+    if buf.len() < size {
+        return Err(Error::InvalidCar("truncated"));
+    }
+    let (value, buf) = buf.split_at(size);
+    let value = value
+        .iter()
+        .fold(0, |value, byte| value << 8 | *byte as u64);
+    Ok((major, value, buf))
+}
+
+/// Read a CBOR item of the `expected` major type
+fn get_cbor(buf: &[u8], expected: u8) -> Result<(u64, &[u8])> {
+    let (major, value, buf) = get_cbor_head(buf)?;
+    if major != expected {
+        return Err(Error::InvalidCar("unexpected CBOR item"));
+    }
+    Ok((value, buf))
+}
+
+fn get_cbor_bytes(buf: &[u8], major: u8) -> Result<(&[u8], &[u8])> {
+    let (len, buf) = get_cbor(buf, major)?;
+    if (buf.len() as u64) < len {
+        return Err(Error::InvalidCar("truncated"));
+    }
+    Ok(buf.split_at(len as usize))
+}
+
+fn encode_header(roots: &[Cid], schemas: &[(BlockType, u32)]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    put_cbor_head(&mut buf, CBOR_MAP, if schemas.is_empty() { 2 } else { 3 });
+    put_cbor_text(&mut buf, "roots");
+    put_cbor_head(&mut buf, CBOR_ARRAY, roots.len() as u64);
+    for root in roots {
+        // DAG-CBOR links are prefixed by the identity multibase
+        let cid = root.to_bytes();
+        put_cbor_head(&mut buf, CBOR_TAG, CID_TAG);
+        put_cbor_head(&mut buf, CBOR_BYTES, cid.len() as u64 + 1);
+        buf.push(0x00);
+        buf.extend_from_slice(&cid);
+    }
+    put_cbor_text(&mut buf, "version");
+    put_cbor_head(&mut buf, CBOR_UINT, CAR_VERSION);
+    if !schemas.is_empty() {
+        put_cbor_text(&mut buf, "schemas");
+        put_cbor_head(&mut buf, CBOR_MAP, schemas.len() as u64);
+        for (block_type, schema) in schemas {
+            put_cbor_head(&mut buf, CBOR_UINT, u64::from(*block_type));
+            put_cbor_head(&mut buf, CBOR_UINT, *schema as u64);
+        }
+    }
+    buf
+}
+
+/// Decode the header, returns the roots and the schema versions
+fn decode_header(buf: &[u8]) -> Result<(Vec<Cid>, Schemas)> {
+    let (entries, mut buf) = get_cbor(buf, CBOR_MAP)?;
+    let mut roots = None;
+    let mut version = None;
+    let mut schemas = Vec::new();
+    for _ in 0..entries {
+        let (key, rest) = get_cbor_bytes(buf, CBOR_TEXT)?;
+        buf = rest;
+        match key {
+            b"roots" => {
+                let (len, rest) = get_cbor(buf, CBOR_ARRAY)?;
+                buf = rest;
+                let mut cids = Vec::new();
+                for _ in 0..len {
+                    let (tag, rest) = get_cbor(buf, CBOR_TAG)?;
+                    if tag != CID_TAG {
+                        return Err(Error::InvalidCar("root isn't a CID"));
+                    }
+                    let (cid, rest) = get_cbor_bytes(rest, CBOR_BYTES)?;
+                    buf = rest;
+                    match cid.split_first() {
+                        Some((0x00, cid)) => cids.push(Cid::from_bytes(cid)?),
+                        _ => return Err(Error::InvalidCar("root isn't a CID")),
+                    }
+                }
+                roots = Some(cids);
+            }
+            b"version" => {
+                let (value, rest) = get_cbor(buf, CBOR_UINT)?;
+                buf = rest;
+                version = Some(value);
+            }
+            b"schemas" => {
+                let (len, rest) = get_cbor(buf, CBOR_MAP)?;
+                buf = rest;
+                for _ in 0..len {
+                    let (code, rest) = get_cbor(buf, CBOR_UINT)?;
+                    let (schema, rest) = get_cbor(rest, CBOR_UINT)?;
+                    buf = rest;
+                    let block_type = BlockType::try_from(code)
+                        .map_err(|_| Error::InvalidCar("unknown block type"))?;
+                    let schema =
+                        u32::try_from(schema).map_err(|_| Error::InvalidCar("invalid schema"))?;
+                    schemas.push((block_type, schema));
+                }
+            }
+            _ => return Err(Error::InvalidCar("unknown header field")),
+        }
+    }
+    if version != Some(CAR_VERSION) {
+        return Err(Error::InvalidCar("unsupported version"));
+    }
+    let roots = roots.ok_or(Error::InvalidCar("missing roots"))?;
+    Ok((roots, schemas))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockType, DeviceKey, MultiHashCode};
+
+    fn blocks() -> Vec<Block> {
+        vec![
+            Block::new(BlockType::BLOB, b"shelter".to_vec()),
+            Block::new(BlockType::FILE, vec![7; 100_000]),
+            Block::with_hasher(BlockType::TREE, vec![], MultiHashCode::Sha2_256).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn round_trip() {
+        let blocks = blocks();
+        let roots = [blocks[1].cid()];
+        let mut writer = CarWriter::new(Vec::new(), &roots).unwrap();
+        for block in &blocks {
+            writer.write_block(block).unwrap();
+        }
+        let car = writer.finish().unwrap();
+
+        // header size, then { "roots": [ tag 42 ...
+        assert_eq!(car[..10], *b"\x3d\xa2\x65roots\x81\xd8");
+
+        let mut reader = CarReader::new(&car[..]).unwrap();
+        assert_eq!(reader.roots(), roots);
+        let read: Vec<Block> = reader.by_ref().collect::<Result<_>>().unwrap();
+        assert_eq!(read.len(), blocks.len());
+        for (read, block) in read.iter().zip(&blocks) {
+            assert_eq!(read.get_block_address(), block.get_block_address());
+            assert_eq!(read.data, block.data);
+        }
+        assert!(reader.next_block().unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_archives() {
+        let mut writer = CarWriter::new(Vec::new(), &[]).unwrap();
+        writer.write_block(&blocks()[0]).unwrap();
+        let car = writer.finish().unwrap();
+        assert!(CarReader::new(&car[..]).unwrap().roots().is_empty());
+
+        let mut tampered = car.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        let mut reader = CarReader::new(&tampered[..]).unwrap();
+        assert!(matches!(reader.next(), Some(Err(Error::HashMismatch))));
+        assert!(reader.next().is_none());
+
+        let mut reader = CarReader::new(&car[..car.len() - 1]).unwrap();
+        assert!(matches!(
+            reader.next_block(),
+            Err(Error::InvalidCar("truncated"))
+        ));
+
+        // the header, then a section size from a corrupted archive
+        let mut oversized = car[..car[0] as usize + 1].to_vec();
+        put_varint(&mut oversized, MAX_SECTION_SIZE + 1);
+        let mut reader = CarReader::new(&oversized[..]).unwrap();
+        assert!(matches!(
+            reader.next_block(),
+            Err(Error::InvalidCar("section too large"))
+        ));
+
+        assert!(CarReader::new(&[][..]).is_err());
+        assert!(CarReader::new(&car[..5]).is_err());
+    }
+
+    #[test]
+    fn schemas() {
+        let mut blocks = blocks();
+        blocks[1].schema = 2;
+        let mut writer = CarWriter::new(Vec::new(), &[]).unwrap();
+        assert!(matches!(
+            writer.write_block(&blocks[1]),
+            Err(Error::InvalidCar(_))
+        ));
+
+        let schemas = [(BlockType::FILE, 2), (BlockType::TREE, 0)];
+        let mut writer = CarWriter::with_schemas(Vec::new(), &[], &schemas).unwrap();
+        for block in &blocks {
+            writer.write_block(block).unwrap();
+        }
+        let mut stale = blocks[1].clone();
+        stale.schema = 1;
+        assert!(writer.write_block(&stale).is_err());
+        let car = writer.finish().unwrap();
+
+        let read: Vec<Block> = CarReader::new(&car[..])
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        let schemas: Vec<u32> = read.iter().map(|block| block.schema).collect();
+        assert_eq!(schemas, [0, 2, 0]);
+    }
+
+    #[test]
+    fn signatures_dropped() {
+        let mut block = blocks().remove(0);
+        block.sign(&DeviceKey::from_bytes(&[7; 32])).unwrap();
+        let mut writer = CarWriter::new(Vec::new(), &[]).unwrap();
+        writer.write_block(&block).unwrap();
+        let car = writer.finish().unwrap();
+
+        let mut reader = CarReader::new(&car[..]).unwrap();
+        let read = reader.next_block().unwrap().unwrap();
+        assert_eq!(read.get_block_address(), block.get_block_address());
+        assert!(read.signer().is_none());
+        assert!(matches!(read.verify_signature(), Err(Error::Unsigned)));
+    }
+}
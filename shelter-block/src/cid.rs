@@ -0,0 +1,151 @@
+use crate::encoding::{get_bytes, get_varint, put_varint};
+use crate::error::{Error, Result};
+use crate::multihash::MultiHash;
+use crate::BlockType;
+use multibase::Base;
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+/// Content identifier of a block, compatible with IPFS CIDv1
+///
+/// <version><codec><multihash>
+///   - version (varint): 1
+///   - codec (varint): multicodec of the block type
+///   - multihash of the block content
+///
+/// The string form is multibase encoded, base32 lower case by default.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cid {
+    codec: BlockType,
+    mh: MultiHash,
+}
+
+impl Cid {
+    pub const VERSION: u64 = 1;
+
+    pub fn new(codec: BlockType, mh: MultiHash) -> Self {
+        Self { codec, mh }
+    }
+
+    /// Block type of the addressed block
+    #[inline]
+    pub fn codec(&self) -> BlockType {
+        self.codec
+    }
+
+    /// Multihash of the addressed block content
+    #[inline]
+    pub fn hash(&self) -> &MultiHash {
+        &self.mh
+    }
+
+    /// Binary form of the CID
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(8 + self.mh.digest().len());
+        put_varint(&mut buf, Self::VERSION);
+        put_varint(&mut buf, u64::from(self.codec));
+        put_varint(&mut buf, self.mh.code() as u64);
+        put_varint(&mut buf, self.mh.digest().len() as u64);
+        buf.extend_from_slice(self.mh.digest());
+        buf
+    }
+
+    /// Parse the binary form of a CID
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let (cid, rest) = Self::read_bytes(data)?;
+        if !rest.is_empty() {
+            return Err(Error::InvalidCid("trailing bytes"));
+        }
+        Ok(cid)
+    }
+
+    /// Parse a CID at the beginning of `data`, returns the remaining bytes
+    pub(crate) fn read_bytes(data: &[u8]) -> Result<(Self, &[u8])> {
+        let (version, buf) = get_varint(data)?;
+        if version != Self::VERSION {
+            return Err(Error::InvalidCid("unsupported version"));
+        }
+        let (codec, buf) = get_varint(buf)?;
+        let codec = BlockType::try_from(codec).map_err(|_| Error::UnknownBlockType(codec))?;
+        let (code, buf) = get_varint(buf)?;
+        let code = u32::try_from(code).map_err(|_| Error::InvalidCid("hash code overflow"))?;
+        let (digest, buf) = get_bytes(buf)?;
+        let mh = MultiHash::from_parts(code, digest.to_vec());
+        Ok((Self::new(codec, mh), buf))
+    }
+
+    /// String form of the CID using a specific multibase
+    pub fn to_string_of_base(&self, base: Base) -> String {
+        multibase::encode(base, self.to_bytes())
+    }
+}
+
+impl Display for Cid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str(&self.to_string_of_base(Base::Base32Lower))
+    }
+}
+
+impl FromStr for Cid {
+    type Err = Error;
+
+    /// Parse a multibase encoded CID, whatever its base
+    fn from_str(s: &str) -> Result<Self> {
+        let (_, data) = multibase::decode(s).map_err(|_| Error::InvalidCid("invalid multibase"))?;
+        Self::from_bytes(&data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MultiHashCode;
+
+    #[test]
+    fn round_trip() {
+        let mh = MultiHashCode::Blake3.digest(b"shelter").unwrap();
+        let cid = Cid::new(BlockType::BLOB, mh.clone());
+        let bytes = cid.to_bytes();
+        assert_eq!(bytes[..7], [0x01, 0xb2, 0x80, 0xc0, 0x01, 0x1e, 0x20]);
+        assert_eq!(&bytes[7..], mh.digest());
+        assert_eq!(Cid::from_bytes(&bytes).unwrap(), cid);
+
+        let string = cid.to_string();
+        assert_eq!(
+            string,
+            "bagzibqabdyqfuymmngdzt7ykywh5xnxkuim2jj5ft72kld3atvxidhl4t6sg64a"
+        );
+        assert_eq!(string.parse::<Cid>().unwrap(), cid);
+        let base58 = cid.to_string_of_base(Base::Base58Btc);
+        assert!(base58.starts_with('z'));
+        assert_eq!(base58.parse::<Cid>().unwrap(), cid);
+
+        let blake2b = Cid::new(
+            BlockType::FILE,
+            MultiHashCode::Blake2b256.digest(b"").unwrap(),
+        );
+        assert_eq!(blake2b.to_bytes()[4..8], [0x01, 0xa0, 0xe4, 0x02]);
+        assert_eq!(Cid::from_bytes(&blake2b.to_bytes()).unwrap(), blake2b);
+    }
+
+    #[test]
+    fn invalid() {
+        let bytes =
+            Cid::new(BlockType::BLOB, MultiHashCode::Blake3.digest(b"").unwrap()).to_bytes();
+        assert!(Cid::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        let mut v0 = bytes.clone();
+        v0[0] = 0x00;
+        assert!(matches!(
+            Cid::from_bytes(&v0),
+            Err(Error::InvalidCid("unsupported version"))
+        ));
+        let mut codec = bytes;
+        codec[1] = 0x55;
+        assert!(matches!(
+            Cid::from_bytes(&codec),
+            Err(Error::UnknownBlockType(0x55))
+        ));
+        assert!("not a cid".parse::<Cid>().is_err());
+    }
+}
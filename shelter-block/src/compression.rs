@@ -0,0 +1,119 @@
+use crate::error::{Error, Result};
+use std::convert::TryFrom;
+
+/// zstd level used to compress blocks
+const ZSTD_LEVEL: i32 = 3;
+
+/// Max lz4 compression ratio, a corrupted size isn't allocated
+const LZ4_MAX_RATIO: usize = 255;
+
+/// Max size of a decompressed content, larger than any block (chunks are at
+/// most 16 MiB)
+const MAX_CONTENT_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Multicodec code of the compression applied to the block content
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u64", into = "u64")]
+pub enum Compression {
+    /// Content stored as is
+    #[default]
+    Identity = 0x00,
+    Zstd = 0x39,
+    Lz4 = 0x3a,
+}
+
+impl Compression {
+    /// Compress some content, `None` when it doesn't get smaller
+    pub fn compress(self, data: &[u8]) -> Result<Option<Vec<u8>>> {
+        let compressed = match self {
+            Self::Identity => return Ok(None),
+            Self::Zstd => zstd::bulk::compress(data, ZSTD_LEVEL)?,
+            Self::Lz4 => lz4_flex::compress_prepend_size(data),
+        };
+        Ok(Some(compressed).filter(|compressed| compressed.len() < data.len()))
+    }
+
+    /// Decompress the content of a block
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            Self::Identity => Ok(data.to_vec()),
+            Self::Zstd => {
+                // the frame records the content size, decompression fails
+                // when the content is larger
+                let size = zstd::zstd_safe::get_frame_content_size(data)
+                    .ok()
+                    .flatten()
+                    .filter(|size| *size <= MAX_CONTENT_SIZE)
+                    .ok_or(Error::Decompress)?;
+                zstd::bulk::decompress(data, size as usize).map_err(|_| Error::Decompress)
+            }
+            Self::Lz4 => {
+                let size = data
+                    .get(..4)
+                    .map(|size| u32::from_le_bytes([size[0], size[1], size[2], size[3]]))
+                    .ok_or(Error::Decompress)?;
+                if size as usize > data.len().saturating_mul(LZ4_MAX_RATIO) {
+                    return Err(Error::Decompress);
+                }
+                lz4_flex::decompress_size_prepended(data).map_err(|_| Error::Decompress)
+            }
+        }
+    }
+}
+
+impl TryFrom<u64> for Compression {
+    type Error = String;
+
+    fn try_from(raw: u64) -> std::result::Result<Self, Self::Error> {
+        match raw {
+            0x00 => Ok(Self::Identity),
+            0x39 => Ok(Self::Zstd),
+            0x3a => Ok(Self::Lz4),
+            _ => Err("invalid code".to_string()),
+        }
+    }
+}
+
+impl From<Compression> for u64 {
+    fn from(code: Compression) -> Self {
+        code as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compress_round_trip() {
+        let text = "a safe place for all your data ".repeat(100);
+        for compression in [Compression::Zstd, Compression::Lz4] {
+            let compressed = compression.compress(text.as_bytes()).unwrap().unwrap();
+            assert!(compressed.len() < text.len() / 4);
+            assert_eq!(
+                compression.decompress(&compressed).unwrap(),
+                text.as_bytes()
+            );
+
+            // incompressible content is kept as is
+            assert!(compression.compress(&[1, 2, 3]).unwrap().is_none());
+            assert!(matches!(
+                compression.decompress(&compressed[..compressed.len() / 2]),
+                Err(Error::Decompress)
+            ));
+        }
+        assert!(Compression::Identity
+            .compress(text.as_bytes())
+            .unwrap()
+            .is_none());
+        assert!(Compression::Lz4.decompress(&[0xff; 8]).is_err());
+
+        // a small content claiming a huge decompressed size
+        let bomb = zstd::bulk::compress(&vec![0; 64 * 1024 * 1024], ZSTD_LEVEL).unwrap();
+        assert!(bomb.len() < 8 * 1024);
+        assert!(matches!(
+            Compression::Zstd.decompress(&bomb),
+            Err(Error::Decompress)
+        ));
+    }
+}
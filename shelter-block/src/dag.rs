@@ -0,0 +1,196 @@
+//! Traversal of the block graph
+//!
+//! Blocks reference other blocks through their [`ShelterBlock::links`].
+//! A [`LinkRegistry`] maps each block type to its payload, so a
+//! [`DagWalker`] can follow the links of any block without knowing its type.
+use crate::error::{Error, Result};
+use crate::multihash::{AddressKey, MultiHashCode};
+use crate::{load_item, Block, BlockAddress, BlockType, ShelterBlock};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+/// Decode the payload of a block and return its links
+type LinkDecoder = fn(&Block) -> Result<Vec<BlockAddress>>;
+
+/// Registry of the payload type of each block type
+///
+/// Blocks of an unregistered type, like blobs, are leaves.
+#[derive(Debug, Default, Clone)]
+pub struct LinkRegistry {
+    decoders: HashMap<BlockType, LinkDecoder>,
+}
+
+impl LinkRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `T` as the payload of the blocks of `block_type`
+    pub fn register<T: ShelterBlock>(&mut self, block_type: BlockType) -> &mut Self {
+        self.decoders.insert(block_type, decode_links::<T>);
+        self
+    }
+
+    /// Addresses of the blocks referenced by `block`
+    pub fn links(&self, block: &Block) -> Result<Vec<BlockAddress>> {
+        match self.decoders.get(&block.block_type) {
+            Some(decoder) => decoder(block),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+fn decode_links<T: ShelterBlock>(block: &Block) -> Result<Vec<BlockAddress>> {
+    Ok(load_item::<T>(block)?.links())
+}
+
+/// Breadth first walker over the blocks reachable from some roots
+///
+/// Blocks are loaded with a callback, so any storage can be walked. Every
+/// block is verified, and visited once even when shared by several parents.
+pub struct DagWalker<F> {
+    registry: LinkRegistry,
+    load: F,
+    key: Option<AddressKey>,
+    queue: VecDeque<BlockAddress>,
+    visited: HashSet<BlockAddress>,
+}
+
+impl<F> DagWalker<F>
+where
+    F: FnMut(&BlockAddress) -> Result<Vec<u8>>,
+{
+    /// Create a walker starting from `roots`, loading the serialized blocks
+    /// with `load`
+    pub fn new<I>(registry: LinkRegistry, roots: I, load: F) -> Self
+    where
+        I: IntoIterator<Item = BlockAddress>,
+    {
+        let mut walker = Self {
+            registry,
+            load,
+            key: None,
+            queue: VecDeque::new(),
+            visited: HashSet::new(),
+        };
+        roots.into_iter().for_each(|root| walker.push(root));
+        walker
+    }
+
+    /// Set the key verifying keyed block addresses
+    pub fn set_key(&mut self, key: Option<AddressKey>) {
+        self.key = key;
+    }
+
+    /// Load and verify the next block, `None` once every reachable block
+    /// was visited
+    pub fn next_block(&mut self) -> Result<Option<(BlockAddress, Block)>> {
+        let address = match self.queue.pop_front() {
+            Some(address) => address,
+            None => return Ok(None),
+        };
+        let block = Block::try_deserialize(&(self.load)(&address)?)?;
+        block.verify_with_key(self.key.as_ref())?;
+        // blocks stored by id are updated in place, the others are content
+        // addressed and must match their address
+        if address.code() != MultiHashCode::Identity && block.try_address()? != address {
+            return Err(Error::HashMismatch);
+        }
+        for link in self.registry.links(&block)? {
+            self.push(link);
+        }
+        Ok(Some((address, block)))
+    }
+
+    fn push(&mut self, address: BlockAddress) {
+        // inline content is part of its parent
+        if address != BlockAddress::INLINE && self.visited.insert(address) {
+            self.queue.push_back(address);
+        }
+    }
+}
+
+impl<F> Iterator for DagWalker<F>
+where
+    F: FnMut(&BlockAddress) -> Result<Vec<u8>>,
+{
+    type Item = Result<(BlockAddress, Block)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_block().transpose()
+    }
+}
+
+impl<F> Debug for DagWalker<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.debug_struct("DagWalker")
+            .field("registry", &self.registry)
+            .field("key", &self.key)
+            .field("queue", &self.queue)
+            .field("visited", &self.visited.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BlockId;
+
+    #[derive(Debug, Serialize, Deserialize)]
+    struct Node {
+        id: BlockId,
+        children: Vec<BlockAddress>,
+    }
+
+    impl ShelterBlock for Node {
+        type ItemBlock = Self;
+
+        const BLOCK_TYPE: BlockType = BlockType::FILE;
+
+        fn links(&self) -> Vec<BlockAddress> {
+            self.children.clone()
+        }
+    }
+
+    #[test]
+    fn walk() {
+        let mut store = HashMap::new();
+        let mut put = |block: Block, address: BlockAddress| {
+            store.insert(address, block.serialize());
+            address
+        };
+        let blob = Block::new(BlockType::BLOB, b"shared".to_vec());
+        let blob = put(blob.clone(), blob.address());
+        let leaf = Node {
+            id: BlockId::new(),
+            children: vec![blob],
+        };
+        let leaf = put(leaf.new_block(), leaf.id.into());
+        let root = Node {
+            id: BlockId::new(),
+            children: vec![leaf, blob],
+        };
+        let root = put(root.new_block(), root.id.into());
+
+        let mut registry = LinkRegistry::new();
+        registry.register::<Node>(BlockType::FILE);
+        let load = |address: &BlockAddress| Ok(store[address].clone());
+        let visited: Vec<_> = DagWalker::new(registry.clone(), [root], load)
+            .map(|item| item.unwrap().0)
+            .collect();
+        assert_eq!(visited, [root, leaf, blob]);
+
+        // without registry, the root is a leaf
+        let walker = DagWalker::new(LinkRegistry::new(), [root], load);
+        assert_eq!(walker.count(), 1);
+
+        // a block stored under a wrong content address
+        let mut store = store.clone();
+        let data = store[&leaf].clone();
+        store.insert(blob, data);
+        let load = |address: &BlockAddress| Ok(store[address].clone());
+        let result: Result<Vec<_>> = DagWalker::new(registry, [root], load).collect();
+        assert!(matches!(result, Err(Error::HashMismatch)));
+    }
+}
@@ -0,0 +1,428 @@
+//! Canonical binary encoding of [`Block`], see `FORMAT.md`
+//!
+//! Blocks written before the canonical encoding are bincode encoded, they
+//! share the signature but have no version byte, and are still decoded.
+use crate::error::{Error, Result};
+use crate::multihash::MultiHash;
+use crate::signing::ED25519_PUB;
+use crate::{Block, BlockSignature, BlockType, Compression, SIGNATURE};
+use bincode::config::Options;
+use std::convert::TryFrom;
+use unsigned_varint::{decode, encode};
+
+/// Version of the canonical encoding, written after the signature
+pub(crate) const VERSION: u8 = 0x04;
+
+/// Third version, without schema version
+const VERSION_3: u8 = 0x03;
+
+/// Second version, without schema version and signer
+const VERSION_2: u8 = 0x02;
+
+/// First version, without compression
+const VERSION_1: u8 = 0x01;
+
+/// Signer code of an unsigned block
+const UNSIGNED: u64 = 0x00;
+
+/// Block encoded with bincode, before the canonical encoding
+#[derive(Deserialize)]
+struct LegacyBlock {
+    signature: (char, char, char, char),
+    mh: MultiHash,
+    block_type: BlockType,
+    #[serde(with = "serde_bytes")]
+    data: Vec<u8>,
+}
+
+/// Encode a block in the canonical format
+pub(crate) fn encode(block: &Block) -> Result<Vec<u8>> {
+    let mut buf = encode_header(block, VERSION, u64::from(block.block_type), true)?;
+    match &block.signer {
+        Some(signer) => {
+            put_varint(&mut buf, ED25519_PUB);
+            buf.extend_from_slice(signer.signer().as_bytes());
+            buf.extend_from_slice(&signer.to_bytes());
+        }
+        None => put_varint(&mut buf, UNSIGNED),
+    }
+    Ok(buf)
+}
+
+/// Message signed by the device, the block without its signer
+///
+/// The layout is frozen, whatever the encoding version: the unsigned third
+/// version with the legacy type code, followed by the schema version when
+/// not 0. Blocks signed before the schema version still verify.
+pub(crate) fn signed_message(block: &Block) -> Result<Vec<u8>> {
+    encode_header(
+        block,
+        VERSION_3,
+        block.block_type.legacy_code(),
+        block.schema != 0,
+    )
+}
+
+/// Encode a block up to its signer
+fn encode_header(
+    block: &Block,
+    version: u8,
+    block_type: u64,
+    with_schema: bool,
+) -> Result<Vec<u8>> {
+    let signature = signature_bytes(block.signature)?;
+    let digest = block.mh.digest();
+    let mut buf = Vec::with_capacity(128 + digest.len() + block.data.len());
+    buf.extend_from_slice(&signature);
+    buf.push(version);
+    put_varint(&mut buf, u64::from(block.compression));
+    put_varint(&mut buf, block.mh.code() as u64);
+    put_varint(&mut buf, digest.len() as u64);
+    buf.extend_from_slice(digest);
+    put_varint(&mut buf, block_type);
+    if with_schema {
+        put_varint(&mut buf, block.schema as u64);
+    }
+    put_varint(&mut buf, block.data.len() as u64);
+    buf.extend_from_slice(&block.data);
+    Ok(buf)
+}
+
+/// Decode a block, in the canonical format or the legacy bincode one
+pub(crate) fn decode(data: &[u8]) -> Result<Block> {
+    if data.len() < SIGNATURE_LEN {
+        return Err(Error::Malformed("truncated"));
+    }
+    let (signature, rest) = data.split_at(SIGNATURE_LEN);
+    let signature = (
+        signature[0] as char,
+        signature[1] as char,
+        signature[2] as char,
+        signature[3] as char,
+    );
+    if signature != SIGNATURE {
+        return Err(Error::InvalidSignature(signature));
+    }
+    match rest.first() {
+        Some(&version @ (VERSION | VERSION_3 | VERSION_2)) => {
+            let (compression, buf) = get_varint(&rest[1..])?;
+            let compression = Compression::try_from(compression)
+                .map_err(|_| Error::UnsupportedCompression(compression))?;
+            let with_schema = version == VERSION;
+            let (mut block, buf) = decode_body(signature, compression, with_schema, buf)?;
+            let buf = match version {
+                VERSION | VERSION_3 => {
+                    let (signer, buf) = decode_signer(buf)?;
+                    block.signer = signer;
+                    buf
+                }
+                _ => buf,
+            };
+            if !buf.is_empty() {
+                return Err(Error::Malformed("trailing bytes"));
+            }
+            Ok(block)
+        }
+        Some(&VERSION_1) => match decode_body(signature, Compression::Identity, false, &rest[1..])?
+        {
+            (block, []) => Ok(block),
+            _ => Err(Error::Malformed("trailing bytes")),
+        },
+        // legacy blocks start with the multihash code, never equal to a version
+        Some(_) => {
+            let legacy: LegacyBlock = bincode::options().deserialize(data)?;
+            Ok(Block {
+                signature: legacy.signature,
+                compression: Compression::Identity,
+                mh: legacy.mh,
+                block_type: legacy.block_type,
+                schema: 0,
+                data: legacy.data,
+                signer: None,
+            })
+        }
+        None => Err(Error::Malformed("truncated")),
+    }
+}
+
+const SIGNATURE_LEN: usize = 4;
+
+/// Decode the multihash, type, schema version and content, returns the
+/// remaining bytes
+///
+/// Versions without schema version have the schema 0.
+fn decode_body(
+    signature: (char, char, char, char),
+    compression: Compression,
+    with_schema: bool,
+    buf: &[u8],
+) -> Result<(Block, &[u8])> {
+    let (code, buf) = get_varint(buf)?;
+    let code = u32::try_from(code).map_err(|_| Error::Malformed("hash code overflow"))?;
+    let (digest, buf) = get_bytes(buf)?;
+    let (block_type, buf) = get_varint(buf)?;
+    // blocks written before the private range use the legacy codes
+    let block_type = BlockType::try_from(block_type)
+        .ok()
+        .or_else(|| BlockType::from_legacy_code(block_type))
+        .ok_or(Error::UnknownBlockType(block_type))?;
+    let (schema, buf) = if with_schema {
+        get_varint(buf)?
+    } else {
+        (0, buf)
+    };
+    let schema = u32::try_from(schema).map_err(|_| Error::Malformed("schema overflow"))?;
+    let (data, buf) = get_bytes(buf)?;
+    let block = Block {
+        signature,
+        compression,
+        mh: MultiHash::from_parts(code, digest.to_vec()),
+        block_type,
+        schema,
+        data: data.to_vec(),
+        signer: None,
+    };
+    Ok((block, buf))
+}
+
+/// Decode the signer of a block, returns the remaining bytes
+fn decode_signer(buf: &[u8]) -> Result<(Option<BlockSignature>, &[u8])> {
+    match get_varint(buf)? {
+        (UNSIGNED, buf) => Ok((None, buf)),
+        (ED25519_PUB, buf) => {
+            if buf.len() < SIGNER_LEN {
+                return Err(Error::Malformed("truncated"));
+            }
+            let (signer, buf) = buf.split_at(SIGNER_LEN);
+            let mut public_key = [0; 32];
+            let mut signature = [0; 64];
+            public_key.copy_from_slice(&signer[..32]);
+            signature.copy_from_slice(&signer[32..]);
+            let signer = BlockSignature::from_parts(public_key, &signature);
+            Ok((Some(signer), buf))
+        }
+        (code, _) => Err(Error::UnsupportedSigner(code)),
+    }
+}
+
+/// Size of an Ed25519 public key and signature
+const SIGNER_LEN: usize = 32 + 64;
+
+fn signature_bytes(signature: (char, char, char, char)) -> Result<[u8; SIGNATURE_LEN]> {
+    let chars = [signature.0, signature.1, signature.2, signature.3];
+    if !chars.iter().all(char::is_ascii) {
+        return Err(Error::InvalidSignature(signature));
+    }
+    Ok(chars.map(|c| c as u8))
+}
+
+pub(crate) fn put_varint(buf: &mut Vec<u8>, value: u64) {
+    buf.extend_from_slice(encode::u64(value, &mut encode::u64_buffer()));
+}
+
+pub(crate) fn get_varint(buf: &[u8]) -> Result<(u64, &[u8])> {
+    decode::u64(buf).map_err(|err| match err {
+        decode::Error::Insufficient => Error::Malformed("truncated"),
+        _ => Error::Malformed("invalid varint"),
+    })
+}
+
+/// Read a varint length prefixed byte string
+pub(crate) fn get_bytes(buf: &[u8]) -> Result<(&[u8], &[u8])> {
+    let (len, buf) = get_varint(buf)?;
+    if (buf.len() as u64) < len {
+        return Err(Error::Malformed("truncated"));
+    }
+    Ok(buf.split_at(len as usize))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Block of type BLOB with the payload "shelter", blake3 addressed
+    const GOLDEN_V4: &str = concat!(
+        "53425631",                                                         // "SBV1"
+        "04",                                                               // version
+        "00",                                                               // uncompressed
+        "1e",                                                               // blake3
+        "20",                                                               // digest size
+        "5a618c698799ff0ac58fdbb6eaa219a4a7a59ff4a58f609d6e819d7c9fa46f70", // digest
+        "b280c001",                                                         // BLOB
+        "00",                                                               // schema
+        "07",                                                               // content size
+        "7368656c746572",                                                   // "shelter"
+        "00",                                                               // unsigned
+    );
+
+    /// The same block, in the third version of the canonical encoding
+    const GOLDEN_V3: &str = concat!(
+        "53425631",
+        "03",
+        "00",
+        "1e",
+        "20",
+        "5a618c698799ff0ac58fdbb6eaa219a4a7a59ff4a58f609d6e819d7c9fa46f70",
+        "32",
+        "07",
+        "7368656c746572",
+        "00",
+    );
+
+    /// The same block, in the second version of the canonical encoding
+    const GOLDEN_V2: &str = concat!(
+        "53425631",
+        "02",
+        "00",
+        "1e",
+        "20",
+        "5a618c698799ff0ac58fdbb6eaa219a4a7a59ff4a58f609d6e819d7c9fa46f70",
+        "32",
+        "07",
+        "7368656c746572",
+    );
+
+    /// The same block, in the first version of the canonical encoding
+    const GOLDEN_V1: &str = concat!(
+        "53425631",
+        "01",
+        "1e",
+        "20",
+        "5a618c698799ff0ac58fdbb6eaa219a4a7a59ff4a58f609d6e819d7c9fa46f70",
+        "32",
+        "07",
+        "7368656c746572",
+    );
+
+    /// The same block, bincode encoded before the canonical encoding
+    const GOLDEN_LEGACY: &str = concat!(
+        "53425631",
+        "1e",
+        "20",
+        "5a618c698799ff0ac58fdbb6eaa219a4a7a59ff4a58f609d6e819d7c9fa46f70",
+        "01",
+        "07",
+        "7368656c746572",
+    );
+
+    fn hex(data: &[u8]) -> String {
+        data.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn unhex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn golden_vectors() {
+        let block = Block::new(BlockType::BLOB, b"shelter".to_vec());
+        assert_eq!(hex(&encode(&block).unwrap()), GOLDEN_V4);
+
+        for golden in [GOLDEN_V4, GOLDEN_V3, GOLDEN_V2, GOLDEN_V1, GOLDEN_LEGACY] {
+            let decoded = decode(&unhex(golden)).unwrap();
+            assert_eq!(decoded.signature, SIGNATURE);
+            assert_eq!(decoded.compression, Compression::Identity);
+            assert_eq!(decoded.block_type, BlockType::BLOB);
+            assert_eq!(decoded.schema, 0);
+            assert_eq!(decoded.mh.digest(), block.mh.digest());
+            assert_eq!(decoded.data, b"shelter");
+            assert!(decoded.signer.is_none());
+        }
+
+        let mut block = block;
+        block.schema = 300;
+        assert_eq!(decode(&encode(&block).unwrap()).unwrap().schema, 300);
+    }
+
+    #[test]
+    fn signed() {
+        let key = crate::DeviceKey::from_bytes(&[7; 32]);
+        let mut block = Block::new(BlockType::BLOB, b"shelter".to_vec());
+        block.sign(&key).unwrap();
+        let data = encode(&block).unwrap();
+        let unsigned = unhex(GOLDEN_V4);
+        assert_eq!(data[..unsigned.len() - 1], unsigned[..unsigned.len() - 1]);
+        assert_eq!(data[unsigned.len() - 1..][..2], [0xed, 0x01]);
+        assert_eq!(data.len(), unsigned.len() + 1 + SIGNER_LEN);
+
+        let decoded = decode(&data).unwrap();
+        assert_eq!(decoded.signer, block.signer);
+        assert_eq!(decoded.verify_signature().unwrap(), &key.public_key());
+        for len in 0..data.len() {
+            assert!(decode(&data[..len]).is_err());
+        }
+
+        // a newer schema version is signed too
+        block.schema = 1;
+        assert!(block.verify_signature().is_err());
+        block.sign(&key).unwrap();
+        let decoded = decode(&encode(&block).unwrap()).unwrap();
+        assert_eq!(decoded.verify_signature().unwrap(), &key.public_key());
+    }
+
+    #[test]
+    fn signed_v3() {
+        // GOLDEN_V3 signed with the same key, before the schema version
+        let data = unhex(concat!(
+            "53425631",
+            "03",
+            "00",
+            "1e",
+            "20",
+            "5a618c698799ff0ac58fdbb6eaa219a4a7a59ff4a58f609d6e819d7c9fa46f70",
+            "32",
+            "07",
+            "7368656c746572",
+            "ed01",
+            "ea4a6c63e29c520abef5507b132ec5f9954776aebebe7b92421eea691446d22c",
+            "a111b6803df9112b5e4c6c6adb6d550fe32ddd8d9c96eeb40ee8ea37b1f365d0",
+            "42f3142b7964f96ec2d2af67bacedfe564db01a0427e50909a241fd4f8127100",
+        ));
+        let key = crate::DeviceKey::from_bytes(&[7; 32]);
+        let decoded = decode(&data).unwrap();
+        assert_eq!(decoded.verify_signature().unwrap(), &key.public_key());
+
+        // re-encoded with the current version, the signature still holds
+        let block = decode(&encode(&decoded).unwrap()).unwrap();
+        assert_eq!(block.verify_signature().unwrap(), &key.public_key());
+    }
+
+    #[test]
+    fn malformed() {
+        let data = unhex(GOLDEN_V4);
+        for len in 0..data.len() {
+            assert!(decode(&data[..len]).is_err());
+        }
+
+        let mut trailing = data.clone();
+        trailing.push(0);
+        assert!(matches!(
+            decode(&trailing),
+            Err(Error::Malformed("trailing bytes"))
+        ));
+
+        let mut unknown_type = data.clone();
+        unknown_type[40] = 0x7f;
+        assert!(matches!(
+            decode(&unknown_type),
+            Err(Error::UnknownBlockType(0x7f))
+        ));
+
+        let mut compression = data.clone();
+        compression[5] = 0x7f;
+        assert!(matches!(
+            decode(&compression),
+            Err(Error::UnsupportedCompression(0x7f))
+        ));
+
+        let mut not_minimal = data;
+        not_minimal.splice(6..7, [0x9e, 0x00]);
+        assert!(matches!(
+            decode(&not_minimal),
+            Err(Error::Malformed("invalid varint"))
+        ));
+    }
+}
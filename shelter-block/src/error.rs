@@ -0,0 +1,95 @@
+use crate::{BlockAddress, BlockType};
+use std::result;
+use thiserror::Error;
+
+/// The error type for encoding, loading, verifying and archiving [`Block`].
+///
+/// [`Block`]: struct.Block.html
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Invalid block signature {0:?}")]
+    InvalidSignature((char, char, char, char)),
+
+    #[error("Unsupported hash function {0:#x}")]
+    UnsupportedHash(u32),
+
+    #[error("Block {0} not found")]
+    MissingBlock(BlockAddress),
+
+    #[error("Block digest does not match its data")]
+    HashMismatch,
+
+    #[error("Keyed block address needs the repository address key")]
+    MissingKey,
+
+    #[error("Block is not signed")]
+    Unsigned,
+
+    #[error("Block signature does not match its content")]
+    BadSignature,
+
+    #[error("Block is not signed by a trusted device")]
+    UntrustedSigner,
+
+    #[error("Unsupported block signer {0:#x}")]
+    UnsupportedSigner(u64),
+
+    #[error("Unexpected block type {found:?}, expected {expected:?}")]
+    UnexpectedBlockType {
+        expected: BlockType,
+        found: BlockType,
+    },
+
+    #[error("Unsupported payload schema version {found}, expected {expected} or lower")]
+    UnsupportedSchema { found: u32, expected: u32 },
+
+    #[error("Unknown block type {0:#x}")]
+    UnknownBlockType(u64),
+
+    #[error("Unsupported compression {0:#x}")]
+    UnsupportedCompression(u64),
+
+    #[error("Cannot decompress block content")]
+    Decompress,
+
+    #[error("Malformed block: {0}")]
+    Malformed(&'static str),
+
+    #[error("Invalid CID: {0}")]
+    InvalidCid(&'static str),
+
+    #[error("Invalid block address: {0}")]
+    InvalidAddress(&'static str),
+
+    #[error("Invalid block id")]
+    InvalidBlockId {
+        #[from]
+        source: xid::ParseIdError,
+    },
+
+    #[error("Invalid CAR archive: {0}")]
+    InvalidCar(&'static str),
+
+    #[error("I/O error")]
+    Io {
+        #[from]
+        source: std::io::Error,
+    },
+
+    #[error("Cannot encode block")]
+    Encode { source: bincode::Error },
+
+    #[error("Cannot decode block")]
+    Decode {
+        #[from]
+        source: bincode::Error,
+    },
+}
+
+/// A specialized [`Result`] type for shelter block operations.
+///
+/// See the [`Error`] for all the errors.
+///
+/// [`Result`]: https://doc.rust-lang.org/std/result/enum.Result.html
+/// [`Error`]: enum.Error.html
+pub type Result<T> = result::Result<T, Error>;
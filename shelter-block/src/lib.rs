@@ -7,109 +7,705 @@ extern crate bincode;
 extern crate multibase;
 extern crate serde;
 extern crate serde_bytes;
+extern crate thiserror;
 extern crate unsigned_varint;
 
 mod block_address;
 mod block_id;
+mod block_ref;
 mod block_type;
+mod car;
+mod cid;
+mod compression;
+mod dag;
+mod encoding;
+mod error;
 mod multihash;
+mod signing;
 
 use bincode::config::Options;
-pub use block_address::BlockAddress;
+pub use block_address::{BlockAddress, MAX_DIGEST_LEN};
 pub use block_id::BlockId;
+pub use block_ref::BlockRef;
 pub use block_type::BlockType;
-use multibase::Base;
-use multihash::{Blacke3, MultiHash};
+pub use car::{CarReader, CarWriter};
+pub use cid::Cid;
+pub use compression::Compression;
+pub use dag::{DagWalker, LinkRegistry};
+pub use error::{Error, Result};
+pub use multihash::{hasher, AddressKey, Hasher, KeyedBlake3, MultiHash, MultiHashCode};
 use serde::{Deserialize, Serialize};
+pub use signing::{BlockSignature, DeviceKey, PublicKey};
+use std::borrow::Cow;
 
 /// Stands for Shelter Block Version 1
 const SIGNATURE: (char, char, char, char) = ('S', 'B', 'V', '1');
 
 /// The shelter-block type has the following binary format :
 ///
-/// <signature><multihash><type><content size><content>
+/// <signature><version><compression><multihash><type><schema><content size><content><signer>
 ///   - 4-byte signature: { 'S', 'B', 'V', '1' }
-///   - multihash
-///   - type
+///   - encoding version (1 byte)
+///   - compression (varint multicodec)
+///   - multihash: hash code (varint), digest size (varint), digest
+///   - type (varint multicodec)
+///   - schema version of the content (varint)
 ///   - content size (varint)
-///   - content of the shelter block
+///   - content of the shelter block, compressed
+///   - signer: none, or the Ed25519 public key and signature of a device
+///
+/// The multihash is the one of the uncompressed content, so the block
+/// address doesn't depend on the compression.
+///
+/// See `FORMAT.md` for the full specification.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Block {
     pub signature: (char, char, char, char),
+    pub compression: Compression,
     pub mh: MultiHash,
     pub block_type: BlockType,
+    #[serde(default)]
+    pub schema: u32, // schema version of the payload, see ShelterBlock::SCHEMA_VERSION
     #[serde(with = "serde_bytes")]
-    pub data: Vec<u8>,
+    pub data: Vec<u8>, // stored content, compressed with `compression`
+    #[serde(default)]
+    pub signer: Option<BlockSignature>, // signature of the device which wrote the block
+}
+
+/// Settings used to create a [`Block`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BlockOptions {
+    pub hasher: MultiHashCode,
+    pub compression: Compression,
+    /// Address blocks with [`KeyedBlake3`] instead of `hasher` when set
+    pub key: Option<AddressKey>,
 }
 
 impl Block {
     pub fn new(block_type: BlockType, data: Vec<u8>) -> Self {
-        let mh = Blacke3::digest(&data);
-        Self {
+        Self::with_hasher(block_type, data, MultiHashCode::default())
+            .expect("the default hash function isn't keyed")
+    }
+
+    /// Create a block addressed with a specific hash function
+    ///
+    /// Fails with [`Error::MissingKey`] for a keyed hash function, use
+    /// [`Block::with_options`] with a key instead.
+    pub fn with_hasher(
+        block_type: BlockType,
+        data: Vec<u8>,
+        hasher: MultiHashCode,
+    ) -> Result<Self> {
+        let mh = hasher.digest(&data)?;
+        Ok(Self {
             signature: SIGNATURE,
+            compression: Compression::Identity,
             mh,
             block_type,
+            schema: 0,
             data,
+            signer: None,
+        })
+    }
+
+    /// Create a block with specific settings
+    ///
+    /// The content is stored uncompressed when compression doesn't make it
+    /// smaller.
+    pub fn with_options(
+        block_type: BlockType,
+        data: Vec<u8>,
+        options: &BlockOptions,
+    ) -> Result<Self> {
+        let mut block = match options.key {
+            Some(key) => Self {
+                signature: SIGNATURE,
+                compression: Compression::Identity,
+                mh: KeyedBlake3::new(key).digest(&data),
+                block_type,
+                schema: 0,
+                data,
+                signer: None,
+            },
+            None => Self::with_hasher(block_type, data, options.hasher)?,
+        };
+        if let Some(compressed) = options.compression.compress(&block.data)? {
+            block.compression = options.compression;
+            block.data = compressed;
         }
+        Ok(block)
     }
 
+    /// Content identifier of the block (CIDv1)
+    pub fn cid(&self) -> Cid {
+        Cid::new(self.block_type, self.mh.clone())
+    }
+
+    /// Address of the block, the string form of its [`Cid`]
     pub fn get_block_address(&self) -> String {
-        multibase::encode(Base::Base58Btc, &self.mh.digest)
+        self.cid().to_string()
+    }
+
+    /// Storage address of the block, panics on failure, see [`try_address`]
+    ///
+    /// [`try_address`]: Block::try_address
+    pub fn address(&self) -> BlockAddress {
+        self.try_address().unwrap()
+    }
+
+    /// Storage address of the block, the multihash of its content
+    pub fn try_address(&self) -> Result<BlockAddress> {
+        BlockAddress::try_from(&self.mh)
     }
 
     pub fn get_block_type(&self) -> BlockType {
         self.block_type
     }
 
-    // TODO: improve perf ?
+    /// Get the uncompressed content, panics when invalid, see [`try_get_data`]
+    ///
+    /// [`try_get_data`]: Block::try_get_data
     pub fn get_data(&self) -> Vec<u8> {
-        self.data.clone()
+        self.try_get_data().unwrap().into_owned()
+    }
+
+    /// Get the uncompressed content
+    pub fn try_get_data(&self) -> Result<Cow<'_, [u8]>> {
+        match self.compression {
+            Compression::Identity => Ok(Cow::Borrowed(&self.data)),
+            compression => compression.decompress(&self.data).map(Cow::Owned),
+        }
     }
 
+    /// Serialize the block, panics on failure, see [`try_serialize`]
+    ///
+    /// [`try_serialize`]: Block::try_serialize
     pub fn serialize(&self) -> Vec<u8> {
-        bincode::options().serialize(self).unwrap()
+        self.try_serialize().unwrap()
+    }
+
+    /// Serialize the block in the canonical binary format
+    pub fn try_serialize(&self) -> Result<Vec<u8>> {
+        encoding::encode(self)
+    }
+
+    /// Deserialize a block, without verifying it
+    ///
+    /// Blocks serialized with bincode, before the canonical binary format,
+    /// are still read.
+    pub fn try_deserialize(data: &[u8]) -> Result<Self> {
+        encoding::decode(data)
+    }
+
+    /// Check the block signature and that the multihash matches the
+    /// uncompressed content
+    pub fn verify(&self) -> Result<()> {
+        self.verify_with_key(None)
+    }
+
+    /// Check the block like [`verify`], using `key` when the block has a
+    /// keyed address
+    ///
+    /// [`verify`]: Block::verify
+    pub fn verify_with_key(&self, key: Option<&AddressKey>) -> Result<()> {
+        if self.signature != SIGNATURE {
+            return Err(Error::InvalidSignature(self.signature));
+        }
+        self.mh.verify_with_key(&self.try_get_data()?, key)
+    }
+
+    /// Sign the block with a device key
+    ///
+    /// The signature covers the message built by the frozen layout of the
+    /// `Signer` section of `FORMAT.md`: the unsigned third version of the
+    /// encoding, with the legacy type code, followed by the schema version
+    /// when not 0. It is not the serialized block: the version byte, the
+    /// type code and the schema field of the fourth version aren't covered
+    /// by the signature as such, the type and schema version are only signed
+    /// through this message.
+    pub fn sign(&mut self, key: &DeviceKey) -> Result<()> {
+        let message = encoding::signed_message(self)?;
+        self.signer = Some(key.sign(&message));
+        Ok(())
+    }
+
+    /// Public key of the device which signed the block, if any
+    pub fn signer(&self) -> Option<&PublicKey> {
+        self.signer.as_ref().map(BlockSignature::signer)
+    }
+
+    /// Check the signature of the block, returns the signer
+    pub fn verify_signature(&self) -> Result<&PublicKey> {
+        let signer = self.signer.as_ref().ok_or(Error::Unsigned)?;
+        signer.verify(&encoding::signed_message(self)?)?;
+        Ok(signer.signer())
+    }
+
+    /// Check that the block is signed by one of the `trusted` devices
+    pub fn verify_signer(&self, trusted: &[PublicKey]) -> Result<()> {
+        let signer = self.verify_signature()?;
+        if !trusted.contains(signer) {
+            return Err(Error::UntrustedSigner);
+        }
+        Ok(())
     }
 }
 
+/// A type stored as the payload of a [`Block`]
+///
+/// Methods panicking on invalid input have a `try_` variant returning an
+/// [`Error`] instead.
 pub trait ShelterBlock: Send + Sync + serde::Serialize + serde::de::DeserializeOwned {
     type ItemBlock: ShelterBlock;
 
-    /// Get block id
-    fn get_block_id(&self) -> BlockId;
+    /// Type of the blocks storing this payload
+    const BLOCK_TYPE: BlockType;
+
+    /// Schema version of the payload, recorded in the header of new blocks
+    ///
+    /// Bump it when the serialized layout changes, and decode the payloads
+    /// of the previous versions in [`upgrade`]. Blocks written before the
+    /// schema versions have version 0.
+    ///
+    /// [`upgrade`]: ShelterBlock::upgrade
+    const SCHEMA_VERSION: u32 = 0;
 
     /// Get block type
-    fn get_block_type(&self) -> BlockType;
+    fn get_block_type(&self) -> BlockType {
+        Self::BLOCK_TYPE
+    }
 
-    /// Get block data
+    /// Addresses of the blocks referenced by this one, none by default
+    ///
+    /// Register the type in a [`LinkRegistry`] to follow its links with a
+    /// [`DagWalker`].
+    fn links(&self) -> Vec<BlockAddress> {
+        Vec::new()
+    }
+
+    /// Decode a payload written with an older `schema` version, called when
+    /// loading a block whose schema version is lower than [`SCHEMA_VERSION`]
+    ///
+    /// Implementations decode the old layout with [`decode_payload`] and
+    /// convert it, upgrading one version at a time. The upgraded item is
+    /// written with the current schema when it is stored again. Older
+    /// versions are rejected by default.
+    ///
+    /// [`SCHEMA_VERSION`]: ShelterBlock::SCHEMA_VERSION
+    fn upgrade(schema: u32, _data: &[u8]) -> Result<Self> {
+        Err(Error::UnsupportedSchema {
+            found: schema,
+            expected: Self::SCHEMA_VERSION,
+        })
+    }
+
+    /// Get block data, panics on failure, see [`try_get_block_data`]
+    ///
+    /// [`try_get_block_data`]: ShelterBlock::try_get_block_data
     fn get_block_data(&self) -> Vec<u8> {
-        bincode::options().serialize(self).unwrap()
+        self.try_get_block_data().unwrap()
+    }
+
+    /// Get block data
+    fn try_get_block_data(&self) -> Result<Vec<u8>> {
+        bincode::options()
+            .serialize(self)
+            .map_err(|source| Error::Encode { source })
     }
 
-    /// Create a new Block
+    /// Create a new Block, panics on failure, see [`try_new_block`]
+    ///
+    /// [`try_new_block`]: ShelterBlock::try_new_block
     fn new_block(&self) -> Block {
-        let block_type = self.get_block_type();
-        let data = self.get_block_data();
-        let mh = Blacke3::digest(&data);
-        Block {
-            signature: SIGNATURE,
-            mh,
-            block_type,
-            data,
-        }
+        self.try_new_block().unwrap()
+    }
+
+    /// Create a new Block, with the default settings
+    fn try_new_block(&self) -> Result<Block> {
+        self.try_new_block_with(&BlockOptions::default())
     }
 
+    /// Create a new Block, addressed and compressed as set in `options`
+    fn try_new_block_with(&self, options: &BlockOptions) -> Result<Block> {
+        let data = self.try_get_block_data()?;
+        let mut block = Block::with_options(self.get_block_type(), data, options)?;
+        block.schema = Self::SCHEMA_VERSION;
+        Ok(block)
+    }
+
+    /// Deserialize and verify a block, panics when invalid, see [`try_load_block`]
+    ///
+    /// [`try_load_block`]: ShelterBlock::try_load_block
     fn load_block(data: &[u8]) -> Block {
-        bincode::options().deserialize(data).unwrap()
+        Self::try_load_block(data).unwrap()
     }
 
-    /// Deserialize vec into Self::ItemBlock
+    /// Deserialize vec into Self::ItemBlock, panics when invalid, see
+    /// [`try_load_from_vec`]
+    ///
+    /// [`try_load_from_vec`]: ShelterBlock::try_load_from_vec
     fn load_from_vec(data: &[u8]) -> Self::ItemBlock {
-        let block = Self::load_block(data);
-        bincode::options().deserialize(&block.data).unwrap()
+        Self::try_load_from_vec(data).unwrap()
     }
 
-    /// Serialize a block
+    /// Create a new Block like [`try_new_block_with`], signed with a device
+    /// key
+    ///
+    /// [`try_new_block_with`]: ShelterBlock::try_new_block_with
+    fn try_new_signed_block(&self, options: &BlockOptions, signer: &DeviceKey) -> Result<Block> {
+        let mut block = self.try_new_block_with(options)?;
+        block.sign(signer)?;
+        Ok(block)
+    }
+
+    /// Deserialize a block, checking its signature and multihash
+    fn try_load_block(data: &[u8]) -> Result<Block> {
+        Self::try_load_block_with_key(data, None)
+    }
+
+    /// Deserialize a block like [`try_load_block`], using `key` to verify
+    /// keyed addresses
+    ///
+    /// [`try_load_block`]: ShelterBlock::try_load_block
+    fn try_load_block_with_key(data: &[u8], key: Option<&AddressKey>) -> Result<Block> {
+        let block = Block::try_deserialize(data)?;
+        block.verify_with_key(key)?;
+        Ok(block)
+    }
+
+    /// Deserialize a verified block into Self::ItemBlock, checking that the
+    /// block type is the one of the item
+    fn try_load_from_vec(data: &[u8]) -> Result<Self::ItemBlock> {
+        Self::try_load_from_vec_with_key(data, None)
+    }
+
+    /// Deserialize a block like [`try_load_from_vec`], using `key` to
+    /// verify keyed addresses
+    ///
+    /// [`try_load_from_vec`]: ShelterBlock::try_load_from_vec
+    fn try_load_from_vec_with_key(
+        data: &[u8],
+        key: Option<&AddressKey>,
+    ) -> Result<Self::ItemBlock> {
+        load_item(&Self::try_load_block_with_key(data, key)?)
+    }
+
+    /// Deserialize a block like [`try_load_from_vec_with_key`], rejecting
+    /// blocks not signed by one of the `trusted` devices
+    ///
+    /// [`try_load_from_vec_with_key`]: ShelterBlock::try_load_from_vec_with_key
+    fn try_load_signed_from_vec(
+        data: &[u8],
+        key: Option<&AddressKey>,
+        trusted: &[PublicKey],
+    ) -> Result<Self::ItemBlock> {
+        let block = Self::try_load_block_with_key(data, key)?;
+        block.verify_signer(trusted)?;
+        load_item(&block)
+    }
+
+    /// Serialize a block, panics on failure, see [`try_serialize`]
+    ///
+    /// [`try_serialize`]: ShelterBlock::try_serialize
     fn serialize(block: &Block) -> Vec<u8> {
-        bincode::options().serialize(block).unwrap()
+        block.serialize()
+    }
+
+    /// Serialize a block
+    fn try_serialize(block: &Block) -> Result<Vec<u8>> {
+        block.try_serialize()
+    }
+}
+
+/// A payload stored in a block updated in place, addressed by its id
+///
+/// Content-addressed payloads (blobs, index nodes) have no id, their address
+/// is the hash of the block.
+pub trait MutableBlock: ShelterBlock {
+    /// Get block id
+    fn get_block_id(&self) -> BlockId;
+}
+
+/// Deserialize a payload encoded by [`ShelterBlock::try_get_block_data`]
+///
+/// Used to decode the older layouts of a payload, see [`ShelterBlock::upgrade`].
+pub fn decode_payload<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T> {
+    Ok(bincode::options().deserialize(data)?)
+}
+
+/// Check the type of a verified block and deserialize its payload,
+/// upgrading older schema versions
+pub(crate) fn load_item<I: ShelterBlock>(block: &Block) -> Result<I> {
+    if block.block_type != I::BLOCK_TYPE {
+        return Err(Error::UnexpectedBlockType {
+            expected: I::BLOCK_TYPE,
+            found: block.block_type,
+        });
+    }
+    let data = block.try_get_data()?;
+    match block.schema {
+        schema if schema == I::SCHEMA_VERSION => decode_payload(&data),
+        schema if schema < I::SCHEMA_VERSION => I::upgrade(schema, &data),
+        // written by a newer release
+        schema => Err(Error::UnsupportedSchema {
+            found: schema,
+            expected: I::SCHEMA_VERSION,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Item {
+        id: BlockId,
+        value: u32,
+    }
+
+    impl ShelterBlock for Item {
+        type ItemBlock = Self;
+
+        const BLOCK_TYPE: BlockType = BlockType::FILE;
+    }
+
+    fn item() -> Item {
+        Item {
+            id: BlockId::get_magic(),
+            value: 42,
+        }
+    }
+
+    #[test]
+    fn verified_load() {
+        let item = item();
+        let data = item.new_block().serialize();
+        assert_eq!(Item::try_load_from_vec(&data).unwrap(), item);
+        assert_eq!(Item::load_from_vec(&data), item);
+    }
+
+    #[test]
+    fn hashers() {
+        let item = item();
+        for code in [MultiHashCode::Sha2_256, MultiHashCode::Blake2b256] {
+            let options = BlockOptions {
+                hasher: code,
+                ..Default::default()
+            };
+            let block = item.try_new_block_with(&options).unwrap();
+            assert_eq!(block.mh.algorithm().unwrap(), code);
+            assert_ne!(
+                block.get_block_address(),
+                item.new_block().get_block_address()
+            );
+            let data = block.serialize();
+            assert_eq!(Item::try_load_from_vec(&data).unwrap(), item);
+        }
+    }
+
+    #[test]
+    fn compressed_blocks() {
+        let text = "a safe place for all your data ".repeat(100).into_bytes();
+        let plain = Block::new(BlockType::BLOB, text.clone());
+        for compression in [Compression::Zstd, Compression::Lz4] {
+            let options = BlockOptions {
+                compression,
+                ..Default::default()
+            };
+            let block = Block::with_options(BlockType::BLOB, text.clone(), &options).unwrap();
+            assert_eq!(block.compression, compression);
+            assert!(block.data.len() < text.len());
+            assert_eq!(block.get_block_address(), plain.get_block_address());
+
+            let data = block.serialize();
+            assert!(data.len() < plain.serialize().len());
+            let loaded = Item::try_load_block(&data).unwrap();
+            assert_eq!(loaded.get_data(), text);
+
+            let small = Block::with_options(BlockType::BLOB, vec![1, 2, 3], &options).unwrap();
+            assert_eq!(small.compression, Compression::Identity);
+        }
+    }
+
+    #[test]
+    fn keyed_blocks() {
+        let item = item();
+        let key = AddressKey::new([42; 32]);
+        let options = BlockOptions {
+            key: Some(key),
+            ..Default::default()
+        };
+        let block = item.try_new_block_with(&options).unwrap();
+        assert_eq!(block.mh.algorithm().unwrap(), MultiHashCode::Blake3Keyed);
+        assert_ne!(
+            block.get_block_address(),
+            item.new_block().get_block_address()
+        );
+
+        let data = block.serialize();
+        assert_eq!(
+            Item::try_load_from_vec_with_key(&data, Some(&key)).unwrap(),
+            item
+        );
+        assert!(matches!(
+            Item::try_load_from_vec(&data),
+            Err(Error::MissingKey)
+        ));
+        let other = AddressKey::new([0; 32]);
+        assert!(matches!(
+            Item::try_load_block_with_key(&data, Some(&other)),
+            Err(Error::HashMismatch)
+        ));
+
+        let options = BlockOptions {
+            hasher: MultiHashCode::Blake3Keyed,
+            ..Default::default()
+        };
+        assert!(matches!(
+            item.try_new_block_with(&options),
+            Err(Error::MissingKey)
+        ));
+    }
+
+    #[test]
+    fn tampered_blocks() {
+        let block = item().new_block();
+
+        let mut tampered = block.clone();
+        *tampered.data.last_mut().unwrap() ^= 1;
+        let data = tampered.serialize();
+        assert!(matches!(
+            Item::try_load_block(&data),
+            Err(Error::HashMismatch)
+        ));
+
+        let mut tampered = block.clone();
+        tampered.signature.3 = '2';
+        let data = tampered.serialize();
+        assert!(matches!(
+            Item::try_load_from_vec(&data),
+            Err(Error::InvalidSignature(('S', 'B', 'V', '2')))
+        ));
+
+        let mut tampered = block.clone();
+        tampered.block_type = BlockType::TREE;
+        let data = tampered.serialize();
+        assert!(matches!(
+            Item::try_load_from_vec(&data),
+            Err(Error::UnexpectedBlockType {
+                expected: BlockType::FILE,
+                found: BlockType::TREE
+            })
+        ));
+
+        // rejected before decoding a payload of another type
+        let blob = Block::new(BlockType::BLOB, vec![0xff; 3]).serialize();
+        assert!(matches!(
+            Item::try_load_from_vec(&blob),
+            Err(Error::UnexpectedBlockType {
+                expected: BlockType::FILE,
+                found: BlockType::BLOB
+            })
+        ));
+
+        let data = block.serialize();
+        assert!(matches!(
+            Item::try_load_block(&data[..data.len() - 1]),
+            Err(Error::Malformed("truncated"))
+        ));
+    }
+
+    #[test]
+    fn signed_blocks() {
+        let item = item();
+        let device = DeviceKey::generate();
+        let relay = DeviceKey::generate();
+        let trusted = [device.public_key()];
+        let options = BlockOptions::default();
+
+        let block = item.try_new_signed_block(&options, &device).unwrap();
+        assert_eq!(block.signer(), Some(&device.public_key()));
+        assert_eq!(block.cid(), item.new_block().cid());
+        let data = block.serialize();
+        assert_eq!(
+            Item::try_load_signed_from_vec(&data, None, &trusted).unwrap(),
+            item
+        );
+
+        // a valid block signed by an unknown device
+        let forged = item.try_new_signed_block(&options, &relay).unwrap();
+        assert!(matches!(
+            Item::try_load_signed_from_vec(&forged.serialize(), None, &trusted),
+            Err(Error::UntrustedSigner)
+        ));
+
+        // a block re-addressed by the relay, keeping the device signature
+        let mut forged = Item { value: 7, ..item }.new_block();
+        forged.signer = block.signer.clone();
+        assert!(matches!(
+            Item::try_load_signed_from_vec(&forged.serialize(), None, &trusted),
+            Err(Error::BadSignature)
+        ));
+
+        assert!(matches!(
+            Item::try_load_signed_from_vec(&item.new_block().serialize(), None, &trusted),
+            Err(Error::Unsigned)
+        ));
+    }
+
+    /// Second schema of `Item`, with a label
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct LabeledItem {
+        id: BlockId,
+        value: u32,
+        label: String,
+    }
+
+    impl ShelterBlock for LabeledItem {
+        type ItemBlock = Self;
+
+        const BLOCK_TYPE: BlockType = BlockType::FILE;
+        const SCHEMA_VERSION: u32 = 1;
+
+        fn upgrade(schema: u32, data: &[u8]) -> Result<Self> {
+            match schema {
+                0 => {
+                    let Item { id, value } = decode_payload(data)?;
+                    Ok(Self {
+                        id,
+                        value,
+                        label: String::new(),
+                    })
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn schema_upgrade() {
+        let old = item().new_block();
+        assert_eq!(old.schema, 0);
+        let upgraded = LabeledItem::try_load_from_vec(&old.serialize()).unwrap();
+        assert_eq!((upgraded.value, upgraded.label.as_str()), (42, ""));
+
+        let labeled = LabeledItem {
+            label: "new".to_string(),
+            ..upgraded
+        };
+        let block = labeled.new_block();
+        assert_eq!(block.schema, 1);
+        let data = block.serialize();
+        assert_eq!(LabeledItem::try_load_from_vec(&data).unwrap(), labeled);
+
+        // a block written by a newer release
+        assert!(matches!(
+            Item::try_load_from_vec(&data),
+            Err(Error::UnsupportedSchema {
+                found: 1,
+                expected: 0
+            })
+        ));
     }
 }
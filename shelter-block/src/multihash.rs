@@ -1,8 +1,48 @@
+use crate::error::Error;
+use sha2::Digest;
 use std::convert::TryFrom;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
 
-#[derive(Debug)]
+/// Multicodec code of the supported hash functions
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(try_from = "u64", into = "u64")]
 pub enum MultiHashCode {
+    /// The input itself, addresses blocks by id, never used as a
+    /// repository hash function
+    Identity = 0x00,
+    Sha2_256 = 0x12,
+    Sha3_256 = 0x16,
+    #[default]
     Blake3 = 0x1e,
+    /// BLAKE3 keyed with a repository secret, see [`KeyedBlake3`]
+    Blake3Keyed = 0x3b,
+    Blake2b256 = 0xb220,
+}
+
+impl MultiHashCode {
+    /// Whether the hash function needs a secret key
+    #[inline]
+    pub fn is_keyed(self) -> bool {
+        self == Self::Blake3Keyed
+    }
+
+    /// Get the hasher of this code from the registry
+    ///
+    /// A keyed hash function has no registered hasher and returns
+    /// [`Error::MissingKey`], use [`KeyedBlake3`] instead.
+    pub fn hasher(self) -> crate::Result<&'static dyn Hasher> {
+        hasher(self.into()).ok_or(Error::MissingKey)
+    }
+
+    /// Hash some input with this hash function
+    ///
+    /// Fails with [`Error::MissingKey`] for a keyed hash function, use
+    /// [`KeyedBlake3`] instead.
+    pub fn digest(self, data: &[u8]) -> crate::Result<MultiHash> {
+        Ok(self.hasher()?.digest(data))
+    }
 }
 
 impl TryFrom<u64> for MultiHashCode {
@@ -10,7 +50,12 @@ impl TryFrom<u64> for MultiHashCode {
 
     fn try_from(raw: u64) -> Result<Self, Self::Error> {
         match raw {
+            0x00 => Ok(Self::Identity),
+            0x12 => Ok(Self::Sha2_256),
+            0x16 => Ok(Self::Sha3_256),
             0x1e => Ok(Self::Blake3),
+            0x3b => Ok(Self::Blake3Keyed),
+            0xb220 => Ok(Self::Blake2b256),
             _ => Err("invalid code".to_string()),
         }
     }
@@ -22,7 +67,7 @@ impl From<MultiHashCode> for u64 {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct MultiHash {
     code: u32, // varint hash function code
     #[serde(with = "serde_bytes")]
@@ -30,16 +75,90 @@ pub struct MultiHash {
 }
 
 impl MultiHash {
-    /// Returns the algorithm used in this multihash.
-    pub fn algorithm(&self) -> MultiHashCode {
-        MultiHashCode::try_from(self.code as u64)
-            .unwrap_or_else(|_| panic!("Should not occur as multihash is known to be valid"))
+    pub(crate) fn from_parts(code: u32, digest: Vec<u8>) -> Self {
+        Self { code, digest }
+    }
+
+    /// Returns the algorithm used in this multihash, an unknown code fails
+    /// with [`Error::UnsupportedHash`]
+    pub fn algorithm(&self) -> crate::Result<MultiHashCode> {
+        MultiHashCode::try_from(self.code as u64).map_err(|_| Error::UnsupportedHash(self.code))
+    }
+
+    /// Returns the hash function code, which may be unknown
+    pub fn code(&self) -> u32 {
+        self.code
     }
 
     /// Returns the hash digest.
     pub fn digest(&self) -> &[u8] {
         &self.digest
     }
+
+    /// Check that `data` hashes to this multihash, with the hash function
+    /// of the stored code
+    pub fn verify(&self, data: &[u8]) -> crate::Result<()> {
+        self.verify_with_key(data, None)
+    }
+
+    /// Check that `data` hashes to this multihash, using `key` when the
+    /// hash function is keyed
+    pub fn verify_with_key(&self, data: &[u8], key: Option<&AddressKey>) -> crate::Result<()> {
+        let digest = if self.code as u64 == u64::from(MultiHashCode::Blake3Keyed) {
+            let key = key.ok_or(Error::MissingKey)?;
+            KeyedBlake3::new(*key).hash(data)
+        } else {
+            hasher(self.code as u64)
+                .ok_or(Error::UnsupportedHash(self.code))?
+                .hash(data)
+        };
+        if digest != self.digest {
+            return Err(Error::HashMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// A hash function producing the digest of a multihash
+pub trait Hasher: Debug + Send + Sync {
+    /// Multicodec code of the hash function
+    fn code(&self) -> MultiHashCode;
+
+    /// Hash some input and return the digest
+    fn hash(&self, data: &[u8]) -> Vec<u8>;
+
+    /// Hash some input and return its multihash
+    fn digest(&self, data: &[u8]) -> MultiHash {
+        MultiHash {
+            code: u64::from(self.code()) as u32,
+            digest: self.hash(data),
+        }
+    }
+}
+
+/// Registry of the supported unkeyed hash functions
+static HASHERS: [&dyn Hasher; 5] = [&Identity, &Blacke3, &Sha2_256, &Sha3_256, &Blake2b256];
+
+/// Get the hasher of a multicodec code, `None` when not supported
+pub fn hasher(code: u64) -> Option<&'static dyn Hasher> {
+    HASHERS
+        .iter()
+        .find(|hasher| u64::from(hasher.code()) == code)
+        .copied()
+}
+
+/// Identity multihash, the digest is the input
+#[derive(Clone, Debug)]
+pub struct Identity;
+
+impl Hasher for Identity {
+    fn code(&self) -> MultiHashCode {
+        MultiHashCode::Identity
+    }
+
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        data.to_vec()
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -47,15 +166,168 @@ pub struct Blacke3;
 
 impl Blacke3 {
     pub const CODE: MultiHashCode = MultiHashCode::Blake3;
+}
 
-    /// Hash some input and return the blacke3 digest.
-    pub fn digest(data: &[u8]) -> MultiHash {
-        let digest = blake3::hash(data);
-        let data: &[u8] = digest.as_bytes();
+impl Hasher for Blacke3 {
+    fn code(&self) -> MultiHashCode {
+        Self::CODE
+    }
 
-        MultiHash {
-            code: Self::CODE as u32,
-            digest: Vec::from(data),
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        blake3::hash(data).as_bytes().to_vec()
+    }
+}
+
+/// Secret key of keyed content addressing
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct AddressKey([u8; 32]);
+
+impl AddressKey {
+    pub fn new(key: [u8; 32]) -> Self {
+        Self(key)
+    }
+}
+
+impl Debug for AddressKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str("AddressKey(..)")
+    }
+}
+
+/// BLAKE3 keyed with a repository secret
+///
+/// Addresses can't be computed without the key, so they don't reveal
+/// whether a known content is stored.
+#[derive(Clone, Debug)]
+pub struct KeyedBlake3 {
+    key: AddressKey,
+}
+
+impl KeyedBlake3 {
+    pub const CODE: MultiHashCode = MultiHashCode::Blake3Keyed;
+
+    pub fn new(key: AddressKey) -> Self {
+        Self { key }
+    }
+}
+
+impl Hasher for KeyedBlake3 {
+    fn code(&self) -> MultiHashCode {
+        Self::CODE
+    }
+
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        blake3::keyed_hash(&self.key.0, data).as_bytes().to_vec()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Sha2_256;
+
+impl Hasher for Sha2_256 {
+    fn code(&self) -> MultiHashCode {
+        MultiHashCode::Sha2_256
+    }
+
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        sha2::Sha256::digest(data).to_vec()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Sha3_256;
+
+impl Hasher for Sha3_256 {
+    fn code(&self) -> MultiHashCode {
+        MultiHashCode::Sha3_256
+    }
+
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        sha3::Sha3_256::digest(data).to_vec()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Blake2b256;
+
+impl Hasher for Blake2b256 {
+    fn code(&self) -> MultiHashCode {
+        MultiHashCode::Blake2b256
+    }
+
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        blake2::Blake2b::<blake2::digest::consts::U32>::digest(data).to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CODES: [MultiHashCode; 4] = [
+        MultiHashCode::Sha2_256,
+        MultiHashCode::Sha3_256,
+        MultiHashCode::Blake3,
+        MultiHashCode::Blake2b256,
+    ];
+
+    #[test]
+    fn known_digests() {
+        // digests of "abc"
+        let expected = [
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85",
+            "bddd813c634239723171ef3fee98579b94964e3bb1cb3e427262c8c068d52319",
+        ];
+        for (code, expected) in CODES.iter().zip(expected) {
+            let mh = code.digest(b"abc").unwrap();
+            let hex: String = mh.digest().iter().map(|b| format!("{:02x}", b)).collect();
+            assert_eq!(hex, expected, "{:?}", code);
+            assert_eq!(mh.algorithm().unwrap(), *code);
         }
     }
+
+    #[test]
+    fn verify_dispatch() {
+        for code in CODES {
+            let mh = code.digest(b"data").unwrap();
+            assert!(mh.verify(b"data").is_ok());
+            assert!(matches!(mh.verify(b"date"), Err(Error::HashMismatch)));
+        }
+
+        let key = AddressKey::new([7; 32]);
+        let keyed = KeyedBlake3::new(key).digest(b"data");
+        assert_ne!(
+            keyed.digest(),
+            MultiHashCode::Blake3.digest(b"data").unwrap().digest()
+        );
+        assert!(keyed.verify_with_key(b"data", Some(&key)).is_ok());
+        assert!(matches!(keyed.verify(b"data"), Err(Error::MissingKey)));
+        assert!(matches!(
+            keyed.verify_with_key(b"data", Some(&AddressKey::new([8; 32]))),
+            Err(Error::HashMismatch)
+        ));
+        assert!(matches!(
+            MultiHashCode::Blake3Keyed.hasher(),
+            Err(Error::MissingKey)
+        ));
+        assert!(matches!(
+            MultiHashCode::Blake3Keyed.digest(b"data"),
+            Err(Error::MissingKey)
+        ));
+
+        let unknown = MultiHash {
+            code: 0x99,
+            digest: vec![],
+        };
+        assert!(matches!(
+            unknown.verify(b"data"),
+            Err(Error::UnsupportedHash(0x99))
+        ));
+        assert!(matches!(
+            unknown.algorithm(),
+            Err(Error::UnsupportedHash(0x99))
+        ));
+    }
 }
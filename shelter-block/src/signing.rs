@@ -0,0 +1,139 @@
+use crate::error::{Error, Result};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+
+/// Multicodec code of an Ed25519 public key, identifies the signature scheme
+pub(crate) const ED25519_PUB: u64 = 0xed;
+
+/// Ed25519 signing key of a device
+///
+/// Each device of a repository signs the blocks it writes with its own key,
+/// peers check the signer against the public keys of the trusted devices.
+#[derive(Clone)]
+pub struct DeviceKey(SigningKey);
+
+impl DeviceKey {
+    /// Generate a new random key
+    pub fn generate() -> Self {
+        let mut secret = [0; 32];
+        getrandom::getrandom(&mut secret).expect("Failed to generate a device key");
+        Self::from_bytes(&secret)
+    }
+
+    pub fn from_bytes(secret: &[u8; 32]) -> Self {
+        Self(SigningKey::from_bytes(secret))
+    }
+
+    /// Secret bytes of the key, to be stored encrypted
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0.to_bytes()
+    }
+
+    /// Public key identifying the device
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey(self.0.verifying_key().to_bytes())
+    }
+
+    /// Sign a message, see [`Block::sign`]
+    ///
+    /// [`Block::sign`]: crate::Block::sign
+    pub fn sign(&self, message: &[u8]) -> BlockSignature {
+        BlockSignature {
+            signer: self.public_key(),
+            signature: self.0.sign(message),
+        }
+    }
+}
+
+impl Debug for DeviceKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "DeviceKey({:?})", self.public_key())
+    }
+}
+
+/// Ed25519 public key of a device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PublicKey([u8; 32]);
+
+impl PublicKey {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+/// Signature of a block by a device
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockSignature {
+    signer: PublicKey,
+    signature: Signature,
+}
+
+impl BlockSignature {
+    pub(crate) fn from_parts(signer: [u8; 32], signature: &[u8; 64]) -> Self {
+        Self {
+            signer: PublicKey(signer),
+            signature: Signature::from_bytes(signature),
+        }
+    }
+
+    /// Public key of the signing device
+    #[inline]
+    pub fn signer(&self) -> &PublicKey {
+        &self.signer
+    }
+
+    pub fn to_bytes(&self) -> [u8; 64] {
+        self.signature.to_bytes()
+    }
+
+    /// Check the signature of `message` with the signer public key
+    ///
+    /// Uses the strict Ed25519 verification, which rejects weak signer keys
+    /// and malleable signatures.
+    pub fn verify(&self, message: &[u8]) -> Result<()> {
+        VerifyingKey::from_bytes(&self.signer.0)
+            .and_then(|key| key.verify_strict(message, &self.signature))
+            .map_err(|_| Error::BadSignature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sign_and_verify() {
+        let key = DeviceKey::generate();
+        let signature = key.sign(b"message");
+        assert_eq!(signature.signer(), &key.public_key());
+        assert!(signature.verify(b"message").is_ok());
+        assert!(matches!(
+            signature.verify(b"forged"),
+            Err(Error::BadSignature)
+        ));
+
+        let restored = DeviceKey::from_bytes(&key.to_bytes());
+        assert_eq!(restored.public_key(), key.public_key());
+        assert_ne!(DeviceKey::generate().public_key(), key.public_key());
+        assert!(!format!("{:?}", key).contains(&format!("{:?}", key.to_bytes())));
+    }
+
+    #[test]
+    fn weak_key() {
+        // the identity point signs every message with R = identity, s = 0
+        let mut identity = [0; 32];
+        identity[0] = 1;
+        let mut signature = [0; 64];
+        signature[0] = 1;
+        let signature = BlockSignature::from_parts(identity, &signature);
+        assert!(matches!(
+            signature.verify(b"message"),
+            Err(Error::BadSignature)
+        ));
+    }
+}
@@ -3,7 +3,7 @@ extern crate shelter_block;
 #[macro_use]
 extern crate serde_derive;
 
-use shelter_block::{Block, BlockId, BlockType, ShelterBlock};
+use shelter_block::{Block, BlockId, BlockType, MutableBlock, ShelterBlock};
 
 pub use bincode::config::Options;
 
@@ -17,12 +17,12 @@ struct Entity {
 impl ShelterBlock for Entity {
     type ItemBlock = Self;
 
-    fn get_block_id(&self) -> BlockId {
-        self.id.clone()
-    }
+    const BLOCK_TYPE: BlockType = BlockType::BLOB;
+}
 
-    fn get_block_type(&self) -> BlockType {
-        BlockType::BLOB
+impl MutableBlock for Entity {
+    fn get_block_id(&self) -> BlockId {
+        self.id
     }
 }
 
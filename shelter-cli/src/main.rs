@@ -52,7 +52,7 @@ fn main() {
     // You can check for the existence of subcommands, and if found use their
     // matches just as you would the top level cmd
     match &cli.command {
-        Some(Commands::Serve { port, host, peers }) => {
+        Some(Commands::Serve { port, .. }) => {
             println!("Start server on port {:?}...", port);
         }
         None => {}
@@ -1,4 +1,9 @@
-use std::{io::Error as IoError, result};
+use fast_cdc::ConfigError;
+use shelter_block::Error as BlockError;
+use std::{
+    io::{Error as IoError, ErrorKind},
+    result,
+};
 use thiserror::Error;
 
 /// The error type for operations with [`Repository`] and [`File`].
@@ -58,6 +63,21 @@ pub enum Error {
     #[error("File is closed")]
     Closed,
 
+    #[error("Invalid file index")]
+    InvalidIndex,
+
+    #[error("Invalid chunker config")]
+    ChunkerConfig {
+        #[from]
+        source: ConfigError,
+    },
+
+    #[error("Invalid block")]
+    Block {
+        #[from]
+        source: BlockError,
+    },
+
     #[error("IoError")]
     Io {
         #[from]
@@ -65,6 +85,16 @@ pub enum Error {
     },
 }
 
+impl From<Error> for IoError {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::Io { source } => source,
+            Error::Block { .. } | Error::InvalidIndex => IoError::new(ErrorKind::InvalidData, err),
+            _ => IoError::new(ErrorKind::Other, err),
+        }
+    }
+}
+
 /// A specialized [`Result`] type for Shelter fs operations.
 ///
 /// See the [`Error`] for all the  errors.
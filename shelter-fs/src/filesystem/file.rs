@@ -1,10 +1,12 @@
 #![allow(clippy::unused_io_amount)]
 use super::{
-    open_file::open_file, FileNode, FileNodeLock, FileNodeReader, FileNodeWriter, FileSystem,
-    FileVersion, Metadata, OpenOptions,
+    open_file::open_file, FileContent, FileNode, FileNodeLock, FileNodeReader, FileNodeWriter,
+    FileSystem, FileVersion, IdGenerator, Metadata, OpenOptions,
 };
 use crate::error::{Error, Result};
 use camino::Utf8Path;
+use fast_cdc::ChunkingAlgorithm;
+use shelter_block::BlockOptions;
 use shelter_storage::{Storage, StorageLock};
 use std::io::{Error as IoError, ErrorKind, Read, Result as IoResult, Seek, SeekFrom, Write};
 use std::sync::{Arc, RwLock};
@@ -14,6 +16,10 @@ use std::sync::{Arc, RwLock};
 pub struct File<S: Storage> {
     pub options: OpenOptions,
     storage: StorageLock<S>,
+    cdc: Arc<dyn ChunkingAlgorithm>,
+    block_options: BlockOptions,
+    inline_limit: usize,
+    ids: IdGenerator,
     position: SeekFrom,
     file_node: FileNodeLock,
     reader: Option<FileNodeReader<S>>,
@@ -21,10 +27,22 @@ pub struct File<S: Storage> {
 }
 
 impl<S: Storage> File<S> {
-    pub(super) fn new(options: OpenOptions, storage: StorageLock<S>, file_node: FileNode) -> Self {
+    pub(super) fn new(
+        options: OpenOptions,
+        storage: StorageLock<S>,
+        cdc: Arc<dyn ChunkingAlgorithm>,
+        block_options: BlockOptions,
+        inline_limit: usize,
+        ids: IdGenerator,
+        file_node: FileNode,
+    ) -> Self {
         Self {
             options,
             storage,
+            cdc,
+            block_options,
+            inline_limit,
+            ids,
             position: SeekFrom::Start(0),
             file_node: Arc::new(RwLock::new(file_node)),
             reader: None,
@@ -110,7 +128,14 @@ impl<S: Storage> Write for File<S> {
         let storage = self.storage.clone();
         if self.writer.is_none() {
             if self.options.contains(OpenOptions::FILE_WRITE) {
-                self.writer = Some(FileNodeWriter::new(storage, self.file_node.clone()));
+                self.writer = Some(FileNodeWriter::new(
+                    storage,
+                    self.file_node.clone(),
+                    self.cdc.clone(),
+                    self.block_options,
+                    self.inline_limit,
+                    FileContent::with_id(self.ids.new_id()),
+                ));
             } else {
                 return Err(IoError::new(
                     ErrorKind::Other,
@@ -144,7 +169,11 @@ impl<S: Storage> Read for File<S> {
         let storage = self.storage.clone();
         if self.reader.is_none() {
             if self.options.contains(OpenOptions::FILE_READ) {
-                self.reader = Some(FileNodeReader::new(storage, self.file_node.clone()));
+                self.reader = Some(FileNodeReader::new(
+                    storage,
+                    self.file_node.clone(),
+                    self.block_options.key,
+                ));
             } else {
                 return Err(IoError::new(
                     ErrorKind::Other,
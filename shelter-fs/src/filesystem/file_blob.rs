@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use shelter_block::{BlockId, BlockType, ShelterBlock};
+use shelter_block::{BlockType, ShelterBlock};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileBlob {
@@ -16,12 +16,5 @@ impl FileBlob {
 impl ShelterBlock for FileBlob {
     type ItemBlock = Self;
 
-    fn get_block_id(&self) -> BlockId {
-        // Not necessary for FileBlob
-        unimplemented!();
-    }
-
-    fn get_block_type(&self) -> BlockType {
-        BlockType::BLOB
-    }
+    const BLOCK_TYPE: BlockType = BlockType::BLOB;
 }
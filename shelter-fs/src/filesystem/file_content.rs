@@ -1,42 +1,109 @@
 use crate::time::Time;
 use serde::{Deserialize, Serialize};
-use shelter_block::{BlockAddress, BlockId, BlockType, ShelterBlock};
+use shelter_block::{
+    decode_payload, BlockAddress, BlockId, BlockRef, BlockType, MutableBlock, Result, ShelterBlock,
+};
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct FileContent {
     pub(super) id: BlockId,
     pub(super) len: usize,
     pub(super) ctime: Time,
-    pub(super) block_address: Vec<BlockAddress>,
+    pub(super) block_address: Vec<BlockRef>,
+    pub(super) depth: u8, // levels of index blocks above the blobs, see FileIndex
 }
 
 impl FileContent {
     /// Create new FileContent
     pub fn new() -> Self {
+        Self::with_id(BlockId::new())
+    }
+
+    /// Create new FileContent with a specific id
+    pub fn with_id(id: BlockId) -> Self {
         Self {
-            id: BlockId::new(),
+            id,
             len: 0,
             ctime: Time::now(),
             block_address: Vec::<_>::new(),
+            depth: 0,
         }
     }
 
     /// Append address
-    pub(super) fn push_block_address(&mut self, address: String, len: usize) {
-        let addr = BlockAddress::new(address, len, self.len);
+    pub(super) fn push_block_address(&mut self, address: BlockAddress, len: usize) {
+        let addr = BlockRef::new(address, len, self.len);
         self.block_address.push(addr);
         self.len += len;
     }
+
+    /// Append a chunk inlined in the content
+    pub(super) fn push_inline(&mut self, data: Vec<u8>) {
+        let len = data.len();
+        self.block_address.push(BlockRef::inline(data, self.len));
+        self.len += len;
+    }
+}
+
+/// Layout of schema 0, chunks referenced by their legacy name
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct FileContentV0 {
+    pub(super) id: BlockId,
+    pub(super) len: usize,
+    pub(super) ctime: Time,
+    pub(super) block_address: Vec<BlockAddressV0>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct BlockAddressV0 {
+    pub(super) address: String, // see BlockAddress::legacy_name
+    pub(super) len: usize,
+    pub(super) offset: usize,
 }
 
 impl ShelterBlock for FileContent {
     type ItemBlock = Self;
 
-    fn get_block_id(&self) -> BlockId {
-        self.id
+    const BLOCK_TYPE: BlockType = BlockType::FVER;
+    /// 1: chunks referenced by a `BlockRef`, inlined or indexed
+    const SCHEMA_VERSION: u32 = 1;
+
+    /// Blobs of the content chunks, or the top level index blocks
+    fn links(&self) -> Vec<BlockAddress> {
+        self.block_address
+            .iter()
+            .filter(|block_ref| !block_ref.is_inline())
+            .map(BlockRef::get_address)
+            .collect()
     }
 
-    fn get_block_type(&self) -> BlockType {
-        BlockType::FVER
+    fn upgrade(schema: u32, data: &[u8]) -> Result<Self> {
+        match schema {
+            0 => {
+                let content: FileContentV0 = decode_payload(data)?;
+                let block_address = content
+                    .block_address
+                    .iter()
+                    .map(|old| {
+                        let address = BlockAddress::from_legacy_name(&old.address)?;
+                        Ok(BlockRef::new(address, old.len, old.offset))
+                    })
+                    .collect::<Result<_>>()?;
+                Ok(Self {
+                    id: content.id,
+                    len: content.len,
+                    ctime: content.ctime,
+                    block_address,
+                    depth: 0,
+                })
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl MutableBlock for FileContent {
+    fn get_block_id(&self) -> BlockId {
+        self.id
     }
 }
@@ -1,6 +1,9 @@
+use super::file_index::{self, FileIndex};
 use super::{FileBlob, FileContent};
-use shelter_block::ShelterBlock;
+use crate::error::{Error, Result};
+use shelter_block::{AddressKey, BlockRef, ShelterBlock};
 use shelter_storage::{Storage, StorageLock};
+use std::borrow::Cow;
 use std::cmp::min;
 use std::io::{Read, Result as IoResult, Seek, SeekFrom};
 
@@ -8,61 +11,80 @@ use std::io::{Read, Result as IoResult, Seek, SeekFrom};
 pub struct FileContentReader<S: Storage> {
     storage: StorageLock<S>,
     file_content: FileContent,
+    key: Option<AddressKey>, // verifies keyed block addresses
+    leaf: Vec<BlockRef>,     // chunks of the last loaded index node
     pos: usize,
 }
 
 impl<S: Storage> FileContentReader<S> {
-    pub fn new(storage: StorageLock<S>, file_content: FileContent) -> Self {
+    pub fn new(
+        storage: StorageLock<S>,
+        file_content: FileContent,
+        key: Option<AddressKey>,
+    ) -> Self {
         Self {
             storage,
             file_content,
+            key,
+            leaf: Vec::new(),
             pos: 0,
         }
     }
 }
 
+impl<S: Storage> FileContentReader<S> {
+    /// Find the chunk at the current position, loading the index node
+    /// covering it when not cached
+    fn find_block(&mut self) -> Result<Option<BlockRef>> {
+        if file_index::find(&self.leaf, self.pos).is_none() {
+            let storage = self.storage.read().unwrap();
+            self.leaf = FileIndex::find_leaf(
+                &*storage,
+                &self.file_content.block_address,
+                self.file_content.depth,
+                self.pos,
+                self.key.as_ref(),
+            )?;
+        }
+        Ok(file_index::find(&self.leaf, self.pos).cloned())
+    }
+}
+
 impl<S: Storage> Read for FileContentReader<S> {
     // TODO[epic=perf] read()
     // See https://github.com/rust-lang/rust/issues/44099
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
-        if buf.is_empty() {
-            return Ok(0);
-        }
-
-        let start_offset = self.pos;
         let mut buf_read = 0;
 
-        for blk_addr in self
-            .file_content
-            .block_address
-            .iter()
-            .skip_while(|e| e.end_offset() <= start_offset)
-        {
-            let start_pos = self.pos - blk_addr.offset();
-            let mut data_left = blk_addr.len() - start_pos;
-
-            while data_left > 0 {
-                let dst = &mut buf[buf_read..];
+        // stop when destination buffer is full or at the end of content
+        while buf_read < buf.len() {
+            let blk_addr = match self.find_block()? {
+                Some(blk_addr) => blk_addr,
+                None => break,
+            };
 
-                // if destination buffer is full, stop reading
-                if dst.is_empty() {
-                    return Ok(buf_read);
+            // inline chunks are embedded in the content
+            let blob = match blk_addr.inline_data() {
+                Some(data) => Cow::Borrowed(data),
+                None => {
+                    let blk_data = self
+                        .storage
+                        .read()
+                        .unwrap()
+                        .get_block(&blk_addr.get_address());
+                    let blob = FileBlob::try_load_from_vec_with_key(&blk_data, self.key.as_ref())
+                        .map_err(Error::from)?;
+                    Cow::Owned(blob.data)
                 }
+            };
 
-                let blk_data = self
-                    .storage
-                    .write()
-                    .unwrap()
-                    .get_block(&blk_addr.get_address());
-                let blob = FileBlob::load_from_vec(&blk_data);
-
-                let read_len = min(data_left, dst.len());
-                dst[..read_len].copy_from_slice(&blob.data[start_pos..read_len]);
-                data_left -= read_len;
-                buf_read += read_len;
-                self.pos += read_len;
-            }
+            let start_pos = self.pos - blk_addr.offset();
+            let read_len = min(blk_addr.len() - start_pos, buf.len() - buf_read);
+            buf[buf_read..buf_read + read_len]
+                .copy_from_slice(&blob[start_pos..start_pos + read_len]);
+            buf_read += read_len;
+            self.pos += read_len;
         }
 
         Ok(buf_read)
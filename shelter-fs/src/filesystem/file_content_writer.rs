@@ -1,5 +1,6 @@
-use super::{FileBlob, FileContent};
-use shelter_block::ShelterBlock;
+use super::{FileBlob, FileContent, FileIndex};
+use crate::error::Error;
+use shelter_block::{BlockOptions, MutableBlock, ShelterBlock};
 use shelter_storage::{Storage, StorageLock};
 use std::io::{Result as IoResult, Seek, SeekFrom, Write};
 
@@ -7,13 +8,22 @@ use std::io::{Result as IoResult, Seek, SeekFrom, Write};
 pub struct FileContentWriter<S: Storage> {
     storage: StorageLock<S>,
     file_content: FileContent,
+    options: BlockOptions, // hash function and compression of the blocks
+    inline_limit: usize,   // max size of the chunks inlined in the file content
 }
 
 impl<S: Storage> FileContentWriter<S> {
-    pub fn new(storage: StorageLock<S>, file_content: FileContent) -> Self {
+    pub fn new(
+        storage: StorageLock<S>,
+        file_content: FileContent,
+        options: BlockOptions,
+        inline_limit: usize,
+    ) -> Self {
         Self {
             storage,
             file_content,
+            options,
+            inline_limit,
         }
     }
 
@@ -24,20 +34,34 @@ impl<S: Storage> FileContentWriter<S> {
 
 impl<S: Storage> Write for FileContentWriter<S> {
     fn write(&mut self, chunk: &[u8]) -> IoResult<usize> {
-        let block = FileBlob::new(chunk.to_owned()).new_block();
-        let address = block.get_block_address();
+        if chunk.len() <= self.inline_limit {
+            self.file_content.push_inline(chunk.to_owned());
+            return Ok(0);
+        }
+        let block = FileBlob::new(chunk.to_owned())
+            .try_new_block_with(&self.options)
+            .map_err(Error::from)?;
+        let address = block.try_address().map_err(Error::from)?;
         let mut storage = self.storage.write().unwrap();
-        self.file_content
-            .push_block_address(address.clone(), chunk.len());
-        storage.put_block(&address, &block.serialize());
+        self.file_content.push_block_address(address, chunk.len());
+        storage.put_block(&address, &block.try_serialize().map_err(Error::from)?);
         Ok(0)
     }
 
     fn flush(&mut self) -> IoResult<()> {
-        let block = self.file_content.new_block();
-        let address = self.file_content.get_block_id().to_string();
         let mut storage = self.storage.write().unwrap();
-        storage.put_block(&address, &block.serialize());
+        // large contents keep the top level of their index, the writer
+        // keeps every chunk to be flushed again
+        let mut file_content = self.file_content.clone();
+        let (entries, depth) =
+            FileIndex::build(&mut *storage, file_content.block_address, &self.options)?;
+        file_content.block_address = entries;
+        file_content.depth = depth;
+        let block = file_content
+            .try_new_block_with(&self.options)
+            .map_err(Error::from)?;
+        let address = file_content.get_block_id().into();
+        storage.put_block(&address, &block.try_serialize().map_err(Error::from)?);
         Ok(())
     }
 }
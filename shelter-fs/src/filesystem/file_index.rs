@@ -0,0 +1,250 @@
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use shelter_block::{AddressKey, BlockAddress, BlockOptions, BlockRef, BlockType, ShelterBlock};
+use shelter_storage::Storage;
+
+/// Max number of entries of an index node, and of a file content
+pub(super) const MAX_ENTRIES: usize = 256;
+
+/// Average number of entries of an index node
+const AVG_ENTRIES: u8 = 64;
+
+/// Node of the index of a large file content (INDX block)
+///
+/// File contents with more than [`MAX_ENTRIES`] chunks are split into a
+/// tree of index nodes, the content keeping the top level entries. Entries
+/// of depth 0 nodes are blobs, the others are index nodes of the level
+/// below. Offsets are relative to the start of the node.
+///
+/// Nodes are content addressed and their boundaries depend on the address
+/// of their entries, not on their position, so versions of a file share
+/// the nodes of the unchanged parts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileIndex {
+    depth: u8,
+    entries: Vec<BlockRef>,
+}
+
+impl FileIndex {
+    /// Build the index of a list of chunks, returns the top level entries
+    /// and the depth of the index
+    pub(super) fn build<S: Storage>(
+        storage: &mut S,
+        mut entries: Vec<BlockRef>,
+        options: &BlockOptions,
+    ) -> Result<(Vec<BlockRef>, u8)> {
+        let mut depth = 0;
+        while entries.len() > MAX_ENTRIES {
+            let mut level = Vec::new();
+            for node in split(&entries) {
+                let start = node[0].offset();
+                let len = node.last().map_or(start, BlockRef::end_offset) - start;
+                let index = FileIndex {
+                    depth,
+                    entries: node
+                        .iter()
+                        .map(|entry| {
+                            let mut entry = entry.clone();
+                            entry.set_offset(entry.offset() - start);
+                            entry
+                        })
+                        .collect(),
+                };
+                let block = index.try_new_block_with(options)?;
+                let address = block.try_address()?;
+                if !storage.is_exist(&address) {
+                    storage.put_block(&address, &block.try_serialize()?);
+                }
+                level.push(BlockRef::new(address, len, start));
+            }
+            entries = level;
+            depth += 1;
+        }
+        Ok((entries, depth))
+    }
+
+    /// Find the depth 0 entries covering `pos`, with absolute offsets,
+    /// loading one index node per level
+    pub(super) fn find_leaf<S: Storage>(
+        storage: &S,
+        entries: &[BlockRef],
+        depth: u8,
+        pos: usize,
+        key: Option<&AddressKey>,
+    ) -> Result<Vec<BlockRef>> {
+        let mut entries = entries.to_vec();
+        for level in (0..depth).rev() {
+            let entry = match find(&entries, pos) {
+                Some(entry) => entry.clone(),
+                None => return Ok(Vec::new()),
+            };
+            let data = storage.get_block(&entry.get_address());
+            let index = FileIndex::try_load_from_vec_with_key(&data, key)?;
+            if index.depth != level {
+                return Err(Error::InvalidIndex);
+            }
+            entries = index.entries;
+            for child in entries.iter_mut() {
+                child.set_offset(child.offset() + entry.offset());
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Find the entry covering `pos`, entries are sorted by offset
+pub(super) fn find(entries: &[BlockRef], pos: usize) -> Option<&BlockRef> {
+    let i = entries.partition_point(|entry| entry.end_offset() <= pos);
+    entries.get(i).filter(|entry| entry.offset() <= pos)
+}
+
+/// Split entries into index nodes, a node ends after an entry whose address
+/// matches the boundary condition, or when full
+fn split(entries: &[BlockRef]) -> Vec<&[BlockRef]> {
+    let mut nodes = Vec::new();
+    let mut start = 0;
+    for (i, entry) in entries.iter().enumerate() {
+        let len = i + 1 - start;
+        // at least 2 entries, each level is half the size of the one below
+        if len >= 2 && (is_boundary(&entry.get_address()) || len == MAX_ENTRIES) {
+            nodes.push(&entries[start..=i]);
+            start = i + 1;
+        }
+    }
+    if start < entries.len() {
+        nodes.push(&entries[start..]);
+    }
+    nodes
+}
+
+#[inline]
+fn is_boundary(address: &BlockAddress) -> bool {
+    address
+        .digest()
+        .last()
+        .map_or(false, |byte| byte % AVG_ENTRIES == 0)
+}
+
+impl ShelterBlock for FileIndex {
+    type ItemBlock = Self;
+
+    const BLOCK_TYPE: BlockType = BlockType::INDX;
+
+    /// Index nodes of the level below, or blobs
+    fn links(&self) -> Vec<BlockAddress> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.is_inline())
+            .map(BlockRef::get_address)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::{FileContent, FileContentReader, FileContentWriter};
+    use shelter_block::{MultiHashCode, MutableBlock};
+    use shelter_storage::{MemoryStorage, XChaCha};
+    use std::io::{Read, Write};
+    use std::sync::{Arc, RwLock};
+
+    fn chunks(count: usize) -> Vec<BlockRef> {
+        (0..count)
+            .map(|i| {
+                let mh = MultiHashCode::Blake3.digest(&i.to_le_bytes()).unwrap();
+                let address = BlockAddress::new(mh.algorithm().unwrap(), mh.digest()).unwrap();
+                BlockRef::new(address, 100, i * 100)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn build_and_find() {
+        let mut storage = MemoryStorage::new(XChaCha::new(3, 1 << 8));
+        storage.init(b"password", b"payload");
+        let options = BlockOptions::default();
+
+        let small = chunks(MAX_ENTRIES);
+        let (root, depth) = FileIndex::build(&mut storage, small.clone(), &options).unwrap();
+        assert_eq!((root, depth), (small, 0));
+
+        let chunks = chunks(40_000);
+        let (root, depth) = FileIndex::build(&mut storage, chunks.clone(), &options).unwrap();
+        assert!(depth >= 2 && root.len() <= MAX_ENTRIES);
+        for pos in [0, 99, 100, 654_321, 3_999_999] {
+            let leaf = FileIndex::find_leaf(&storage, &root, depth, pos, None).unwrap();
+            assert_eq!(find(&leaf, pos), Some(&chunks[pos / 100]));
+        }
+        assert!(
+            FileIndex::find_leaf(&storage, &root, depth, 4_000_000, None)
+                .unwrap()
+                .is_empty()
+        );
+
+        // a new version of the file shares most index nodes
+        let mut modified = chunks;
+        modified[10_000] = self::chunks(40_001)[40_000].clone();
+        modified[10_000].set_offset(1_000_000);
+        let (other, _) = FileIndex::build(&mut storage, modified, &options).unwrap();
+        let shared = other.iter().filter(|entry| root.contains(entry)).count();
+        assert!(shared > 0 && shared + 2 >= root.len());
+    }
+
+    #[test]
+    fn read_indexed_content() {
+        let mut storage = MemoryStorage::new(XChaCha::new(3, 1 << 8));
+        storage.init(b"password", b"payload");
+        let storage = Arc::new(RwLock::new(storage));
+        let options = BlockOptions::default();
+
+        let data: Vec<u8> = (0..20_000u32).flat_map(u32::to_le_bytes).collect();
+        let mut writer = FileContentWriter::new(storage.clone(), FileContent::new(), options, 0);
+        // each write stores a whole chunk, as done by the chunker
+        for chunk in data.chunks(100) {
+            assert_eq!(writer.write(chunk).unwrap(), 0);
+        }
+        writer.flush().unwrap();
+
+        let id = writer.get_file_content().get_block_id();
+        let stored = storage.read().unwrap().get_block(&id.into());
+        let file_content = FileContent::load_from_vec(&stored);
+        assert!(file_content.depth > 0 && file_content.block_address.len() <= MAX_ENTRIES);
+
+        let mut buf = Vec::new();
+        FileContentReader::new(storage, file_content, None)
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, data);
+    }
+
+    #[test]
+    fn read_inline_chunks() {
+        let mut storage = MemoryStorage::new(XChaCha::new(3, 1 << 8));
+        storage.init(b"password", b"payload");
+        let storage = Arc::new(RwLock::new(storage));
+        let options = BlockOptions::default();
+
+        let chunks = [vec![1; 100], vec![2; 10], vec![3; 100], vec![4; 64]];
+        let mut writer = FileContentWriter::new(storage.clone(), FileContent::new(), options, 64);
+        for chunk in &chunks {
+            assert_eq!(writer.write(chunk).unwrap(), 0);
+        }
+        writer.flush().unwrap();
+
+        let file_content = writer.get_file_content().clone();
+        let inline: Vec<_> = file_content
+            .block_address
+            .iter()
+            .map(BlockRef::is_inline)
+            .collect();
+        assert_eq!(inline, [false, true, false, true]);
+        assert_eq!(file_content.links().len(), 2);
+
+        let mut buf = Vec::new();
+        FileContentReader::new(storage, file_content, None)
+            .read_to_end(&mut buf)
+            .unwrap();
+        assert_eq!(buf, chunks.concat());
+    }
+}
@@ -5,7 +5,7 @@ use super::Metadata;
 use crate::error::Result;
 use crate::time::Time;
 use serde::{Deserialize, Serialize};
-use shelter_block::{BlockId, BlockType, ShelterBlock};
+use shelter_block::{AddressKey, BlockAddress, BlockId, BlockType, MutableBlock, ShelterBlock};
 use shelter_storage::{Storage, StorageLock};
 use std::sync::{Arc, RwLock};
 
@@ -25,9 +25,14 @@ pub struct FileNode {
 
 impl FileNode {
     pub fn new(name: String, file_type: FileType) -> Self {
+        Self::with_id(BlockId::new(), name, file_type)
+    }
+
+    /// Create a file node with a specific id
+    pub fn with_id(id: BlockId, name: String, file_type: FileType) -> Self {
         let now = Time::now();
         Self {
-            id: BlockId::new(),
+            id,
             name,
             file_type,
             version: 0,
@@ -141,13 +146,18 @@ impl FileNode {
         });
     }
 
-    pub fn clone_current_content<S: Storage>(&self, storage: StorageLock<S>) -> FileContent {
+    pub fn clone_current_content<S: Storage>(
+        &self,
+        storage: StorageLock<S>,
+        key: Option<&AddressKey>,
+        id: BlockId,
+    ) -> Result<FileContent> {
         let file_version = self.get_current_version();
         let content_id = file_version.id;
-        let data = storage.read().unwrap().get_block(&content_id.to_string());
-        let mut file_content = FileContent::load_from_vec(&data);
-        file_content.id = BlockId::new();
-        file_content
+        let data = storage.read().unwrap().get_block(&content_id.into());
+        let mut file_content = FileContent::try_load_from_vec_with_key(&data, key)?;
+        file_content.id = id;
+        Ok(file_content)
     }
 
     // Get reader for sepcified version number
@@ -160,12 +170,20 @@ impl FileNode {
 impl ShelterBlock for FileNode {
     type ItemBlock = Self;
 
-    fn get_block_id(&self) -> BlockId {
-        self.id
+    const BLOCK_TYPE: BlockType = BlockType::FILE;
+
+    /// Contents of the file versions
+    fn links(&self) -> Vec<BlockAddress> {
+        self.versions
+            .iter()
+            .map(|version| version.id.into())
+            .collect()
     }
+}
 
-    fn get_block_type(&self) -> BlockType {
-        BlockType::FILE
+impl MutableBlock for FileNode {
+    fn get_block_id(&self) -> BlockId {
+        self.id
     }
 }
 
@@ -173,6 +191,7 @@ impl ShelterBlock for FileNode {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::error::Error;
 
     #[test]
     fn test_serialize() {
@@ -183,6 +202,30 @@ mod test {
         assert_eq!(node, node2);
     }
 
+    #[test]
+    fn test_corrupted_block() {
+        let node = FileNode::new(String::from("test"), FileType::File);
+        let mut data = node.try_new_block().unwrap().try_serialize().unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0xff;
+        let err = FileNode::try_load_from_vec(&data).map_err(Error::from);
+        assert!(matches!(err, Err(Error::Block { .. })));
+    }
+
+    #[test]
+    fn test_links() {
+        let mut node = FileNode::new(String::from("test"), FileType::File);
+        assert!(node.links().is_empty());
+        let content = FileContent::new();
+        node.add_version(&content);
+        node.add_version(&FileContent::new());
+        assert_eq!(node.links().len(), 2);
+        assert_eq!(node.links()[0], BlockAddress::from(content.id));
+
+        let registry = super::super::link_registry();
+        assert_eq!(registry.links(&node.new_block()).unwrap(), node.links());
+    }
+
     #[test]
     fn test_clone_content() {}
 }
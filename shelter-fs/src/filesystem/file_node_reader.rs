@@ -1,5 +1,6 @@
 use super::{FileContent, FileContentReader, FileNodeLock};
-use shelter_block::ShelterBlock;
+use crate::error::Error;
+use shelter_block::{AddressKey, ShelterBlock};
 use shelter_storage::{Storage, StorageLock};
 use std::io::{Read, Result as IoResult, Seek, SeekFrom};
 
@@ -8,38 +9,43 @@ use std::io::{Read, Result as IoResult, Seek, SeekFrom};
 pub struct FileNodeReader<S: Storage> {
     storage: StorageLock<S>,
     file_node: FileNodeLock,
+    key: Option<AddressKey>, // verifies keyed block addresses
     reader: Option<FileContentReader<S>>,
 }
 
 impl<S: Storage> FileNodeReader<S> {
-    pub fn new(storage: StorageLock<S>, file_node: FileNodeLock) -> Self {
+    pub fn new(storage: StorageLock<S>, file_node: FileNodeLock, key: Option<AddressKey>) -> Self {
         Self {
             storage,
             file_node,
+            key,
             reader: None,
         }
     }
 
     /// Create a reader for current version
-    fn get_reader(&mut self) -> &mut FileContentReader<S> {
-        self.reader.get_or_insert_with(|| {
+    fn get_reader(&mut self) -> IoResult<&mut FileContentReader<S>> {
+        if self.reader.is_none() {
             let node = self.file_node.read().unwrap();
             let block_id = node.get_current_block_id();
-            let data = self
-                .storage
-                .read()
-                .unwrap()
-                .get_block(&block_id.to_string());
-            let file_content = FileContent::load_from_vec(&data);
-            FileContentReader::new(self.storage.clone(), file_content)
-        })
+            let data = self.storage.read().unwrap().get_block(&block_id.into());
+            let file_content = FileContent::try_load_from_vec_with_key(&data, self.key.as_ref())
+                .map_err(Error::from)?;
+            drop(node);
+            self.reader = Some(FileContentReader::new(
+                self.storage.clone(),
+                file_content,
+                self.key,
+            ));
+        }
+        Ok(self.reader.as_mut().unwrap())
     }
 }
 
 impl<S: Storage> Read for FileNodeReader<S> {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
-        let reader = self.get_reader();
+        let reader = self.get_reader()?;
         reader.read(buf)
     }
 }
@@ -47,7 +53,7 @@ impl<S: Storage> Read for FileNodeReader<S> {
 impl<S: Storage> Seek for FileNodeReader<S> {
     #[inline]
     fn seek(&mut self, pos: SeekFrom) -> IoResult<u64> {
-        let reader = self.get_reader();
+        let reader = self.get_reader()?;
         reader.seek(pos)
     }
 }
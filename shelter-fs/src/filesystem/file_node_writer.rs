@@ -1,24 +1,35 @@
 use super::{FileContent, FileContentWriter, FileNodeLock};
-use fast_cdc::Chunker;
-use shelter_block::ShelterBlock;
+use crate::error::Error;
+use fast_cdc::{Chunker, ChunkingAlgorithm};
+use shelter_block::{BlockOptions, MutableBlock, ShelterBlock};
 use shelter_storage::{Storage, StorageLock};
 use std::io::{Result as IoResult, Write};
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct FileNodeWriter<S: Storage> {
-    chunker: Chunker<FileContentWriter<S>>,
+    chunker: Chunker<FileContentWriter<S>, Arc<dyn ChunkingAlgorithm>>,
     file_node: FileNodeLock,
     storage: StorageLock<S>,
+    options: BlockOptions,
 }
 
 impl<S: Storage> FileNodeWriter<S> {
-    pub fn new(storage: StorageLock<S>, file_node: FileNodeLock) -> Self {
-        let file_content = FileContent::new();
-        let file_content_writer = FileContentWriter::new(storage.clone(), file_content);
+    pub fn new(
+        storage: StorageLock<S>,
+        file_node: FileNodeLock,
+        cdc: Arc<dyn ChunkingAlgorithm>,
+        options: BlockOptions,
+        inline_limit: usize,
+        file_content: FileContent,
+    ) -> Self {
+        let file_content_writer =
+            FileContentWriter::new(storage.clone(), file_content, options, inline_limit);
         Self {
-            chunker: Chunker::new(file_content_writer),
+            chunker: Chunker::with_cdc(file_content_writer, cdc),
             file_node,
             storage,
+            options,
         }
     }
 }
@@ -33,10 +44,12 @@ impl<S: Storage> Write for FileNodeWriter<S> {
         let file_content = self.chunker.into_inner().get_file_content();
         let mut node = self.file_node.write().unwrap();
         node.add_version(file_content);
-        let block = node.new_block();
-        let address = node.get_block_id().to_string();
+        let block = node
+            .try_new_block_with(&self.options)
+            .map_err(Error::from)?;
+        let address = node.get_block_id().into();
         let mut storage = self.storage.write().unwrap();
-        storage.put_block(&address, &block.serialize());
+        storage.put_block(&address, &block.try_serialize().map_err(Error::from)?);
         Ok(())
     }
 }
@@ -0,0 +1,29 @@
+use shelter_block::BlockId;
+
+/// Generator of the ids of new blocks
+///
+/// Ids embed the machine id of the host, or one derived from the actor id
+/// of the tree replica with [`FileSystemOptions::REPO_ACTOR_IDS`].
+///
+/// [`FileSystemOptions::REPO_ACTOR_IDS`]: super::FileSystemOptions::REPO_ACTOR_IDS
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IdGenerator {
+    actor: Option<String>,
+}
+
+impl IdGenerator {
+    /// Ids with the machine id derived from `actor`
+    pub fn with_actor(actor: &str) -> Self {
+        Self {
+            actor: Some(actor.to_string()),
+        }
+    }
+
+    /// Create a new id
+    pub fn new_id(&self) -> BlockId {
+        match &self.actor {
+            Some(actor) => BlockId::with_actor(actor),
+            None => BlockId::new(),
+        }
+    }
+}
@@ -5,14 +5,18 @@ mod file_blob;
 mod file_content;
 mod file_content_reader;
 mod file_content_writer;
+mod file_index;
 mod file_node;
 mod file_node_reader;
 mod file_node_writer;
 mod file_type;
 mod file_version;
+mod id_generator;
 mod metadata;
 mod open_file;
 mod open_options;
+#[cfg(test)]
+mod schema_fixtures;
 mod tree;
 
 pub use dir_entry::DirEntry;
@@ -21,20 +25,27 @@ pub use file_blob::FileBlob;
 pub use file_content::FileContent;
 pub use file_content_reader::FileContentReader;
 pub use file_content_writer::FileContentWriter;
+pub use file_index::FileIndex;
 pub use file_node::{FileNode, FileNodeLock};
 pub use file_node_reader::FileNodeReader;
 pub use file_node_writer::FileNodeWriter;
 pub use file_type::FileType;
 pub use file_version::FileVersion;
+pub use id_generator::IdGenerator;
 pub use metadata::Metadata;
 pub use open_options::OpenOptions;
-pub use tree::{Tree, TreeLock};
+pub use tree::{Tree, TreeLock, DEFAULT_ACTOR};
 
 use crate::error::{Error, Result};
+use crate::repository::RepositoryConfig;
 use camino::{Utf8Path, Utf8PathBuf};
 use crdt_tree::{Clock, OpMove};
+use fast_cdc::ChunkingAlgorithm;
 use serde::{Deserialize, Serialize};
-use shelter_block::{BlockId, ShelterBlock};
+use shelter_block::{
+    Block, BlockAddress, BlockId, BlockOptions, BlockType, DagWalker, Error as BlockError,
+    LinkRegistry, ShelterBlock,
+};
 use shelter_storage::{Storage, StorageLock};
 use std::sync::{Arc, RwLock};
 
@@ -50,6 +61,8 @@ bitflags! {
         const REPO_READ_ONLY     = 0b0000_0001;
         const REPO_VERSIONED     = 0b0000_0010;
         // const REPO_DEDUPLICATION = 0b0000_0010;
+        // derive the machine part of new block ids from the replica actor
+        const REPO_ACTOR_IDS     = 0b0000_0100;
     }
 }
 impl Default for FileSystemOptions {
@@ -61,6 +74,11 @@ impl Default for FileSystemOptions {
 /// Rusty shelter file system
 pub struct FileSystem<S: Storage> {
     options: FileSystemOptions,
+    config: RepositoryConfig,
+    cdc: Arc<dyn ChunkingAlgorithm>,
+    block_options: BlockOptions,
+    actor: String, // actor of the tree replica created by init
+    ids: IdGenerator,
     pub tree: Option<TreeLock>,
     pub storage: StorageLock<S>,
 }
@@ -70,32 +88,131 @@ impl<S: Storage> FileSystem<S> {
 
     /// New file system
     pub fn new(options: FileSystemOptions, storage: S) -> Self {
+        Self::with_config(options, RepositoryConfig::default(), storage)
+    }
+
+    /// New file system with repository config used on creation
+    pub fn with_config(options: FileSystemOptions, config: RepositoryConfig, storage: S) -> Self {
         Self {
             options,
+            cdc: config.algorithm.build(config.chunker),
+            block_options: config.block_options(),
+            config,
+            actor: String::from(DEFAULT_ACTOR),
+            ids: IdGenerator::default(),
             tree: None,
             storage: Arc::new(RwLock::new(storage)),
         }
     }
 
+    /// Set the actor id of the tree replica created by [`init`]
+    ///
+    /// An existing repository keeps the actor of its tree.
+    ///
+    /// [`init`]: FileSystem::init
+    #[inline]
+    pub fn set_actor(&mut self, actor: &str) {
+        self.actor = actor.to_string();
+    }
+
+    /// Id generator of an actor, as set in the options
+    fn id_generator(&self, actor: &str) -> IdGenerator {
+        if self.options.contains(FileSystemOptions::REPO_ACTOR_IDS) {
+            IdGenerator::with_actor(actor)
+        } else {
+            IdGenerator::default()
+        }
+    }
+
     // Init file system storage
     #[inline]
-    pub fn init(&mut self, _name: &str, password: &str) {
+    pub fn init(&mut self, name: &str, password: &str) -> Result<()> {
         let mut storage = self.storage.write().unwrap();
         if storage.is_init() {
-            let data = storage.open(password.as_bytes());
-            self.tree = Some(Arc::new(RwLock::new(Tree::load_from_vec(&data))));
+            // Load repository config from super block payload
+            let payload = storage.open(password.as_bytes());
+            let config = if Block::try_deserialize(&payload)?.block_type == BlockType::TREE {
+                // created before the repository config, store its tree as
+                // the other blocks
+                let tree = Tree::try_load_from_vec(&payload)?;
+                let address = tree.id.into();
+                if !storage.is_exist(&address) {
+                    storage.put_block(&address, &tree.try_new_block()?.try_serialize()?);
+                }
+                RepositoryConfig::legacy(tree.id)
+            } else {
+                RepositoryConfig::try_load_from_vec(&payload)?
+            };
+            config.chunker.validate()?;
+            self.block_options = config.new_block_options(&*storage);
+
+            let data = storage.get_block(&config.tree_id.into());
+            let tree = Tree::try_load_from_vec_with_key(&data, self.block_options.key.as_ref())?;
+            self.ids = self.id_generator(tree.actor());
+            self.tree = Some(Arc::new(RwLock::new(tree)));
+            self.config = config;
         } else {
-            let tree = Tree::new();
-            let block = tree.new_block();
+            self.config.chunker.validate()?;
+            self.config.name = name.to_string();
+
+            // Store repository config into super block payload
+            self.ids = self.id_generator(&self.actor);
+            let tree = Tree::new(&self.actor, &self.ids);
+            self.config.id = self.ids.new_id();
+            self.config.tree_id = tree.id;
+            storage.init(
+                password.as_bytes(),
+                &self
+                    .config
+                    .try_new_block_with(&self.config.block_options())?
+                    .try_serialize()?,
+            );
+            self.block_options = self.config.new_block_options(&*storage);
+            storage.put_block(
+                &tree.id.into(),
+                &tree
+                    .try_new_block_with(&self.block_options)?
+                    .try_serialize()?,
+            );
             self.tree = Some(Arc::new(RwLock::new(tree)));
-            storage.init(password.as_bytes(), &block.serialize());
         }
+        self.cdc = self.config.new_cdc(&*storage);
+        Ok(())
     }
 
     pub fn save() -> bool {
         true
     }
 
+    #[inline]
+    pub fn config(&self) -> &RepositoryConfig {
+        &self.config
+    }
+
+    /// Get the settings of the blocks written to the repository
+    #[inline]
+    pub(crate) fn block_options(&self) -> &BlockOptions {
+        &self.block_options
+    }
+
+    /// Max size of the chunks inlined in their file content
+    #[inline]
+    pub(crate) fn inline_limit(&self) -> usize {
+        self.config.inline_limit
+    }
+
+    /// Generator of the ids of new blocks
+    #[inline]
+    pub(crate) fn ids(&self) -> &IdGenerator {
+        &self.ids
+    }
+
+    /// Get the chunking algorithm used to split file content
+    #[inline]
+    pub fn cdc(&self) -> &Arc<dyn ChunkingAlgorithm> {
+        &self.cdc
+    }
+
     #[inline]
     pub fn is_read_only(&self) -> bool {
         self.options.contains(FileSystemOptions::REPO_READ_ONLY)
@@ -120,9 +237,12 @@ impl<S: Storage> FileSystem<S> {
         self.get_path_id(path).is_none()
     }
 
-    pub(crate) fn open_fnode_with_id(&self, node_id: BlockId) -> FileNode {
-        let data = self.storage.read().unwrap().get_block(&node_id.to_string());
-        FileNode::load_from_vec(&data)
+    pub(crate) fn open_fnode_with_id(&self, node_id: BlockId) -> Result<FileNode> {
+        let data = self.storage.read().unwrap().get_block(&node_id.into());
+        Ok(FileNode::try_load_from_vec_with_key(
+            &data,
+            self.block_options.key.as_ref(),
+        )?)
     }
 
     /// Open an existing FileNode
@@ -135,7 +255,7 @@ impl<S: Storage> FileSystem<S> {
 
         // 2. Get file node
         let node_id = self.get_path_id(path).ok_or(Error::NotFound)?;
-        let node = self.open_fnode_with_id(node_id);
+        let node = self.open_fnode_with_id(node_id)?;
 
         Ok(node)
     }
@@ -160,14 +280,16 @@ impl<S: Storage> FileSystem<S> {
 
         // 3. Create file node
         let file_name = path.file_name().ok_or(Error::InvalidPath)?;
-        let node = FileNode::new(file_name.to_string(), file_type);
+        let node = FileNode::with_id(self.ids.new_id(), file_name.to_string(), file_type);
 
         // 4. Write file node into storage
         // self.store_paths.insert(path.to_owned(), node.id);
-        self.storage
-            .write()
-            .unwrap()
-            .put_block(&node.id.to_string(), &node.new_block().serialize());
+        self.storage.write().unwrap().put_block(
+            &node.id.into(),
+            &node
+                .try_new_block_with(&self.block_options)?
+                .try_serialize()?,
+        );
 
         // 5. Update crdt
         let ops = self
@@ -224,15 +346,14 @@ impl<S: Storage> FileSystem<S> {
             .iter()
             .map(|path| {
                 let node_id = self.get_path_id(path).expect("path should exist");
-                let data = self.storage.read().unwrap().get_block(&node_id.to_string());
-                let node = FileNode::load_from_vec(&data);
-                DirEntry {
+                let node = self.open_fnode_with_id(node_id)?;
+                Ok(DirEntry {
                     path: base_path.join(&node.name),
                     name: node.file_name().to_string(),
                     metadata: node.metadata(),
-                }
+                })
             })
-            .collect();
+            .collect::<Result<_>>()?;
 
         Ok(ret)
     }
@@ -245,8 +366,7 @@ impl<S: Storage> FileSystem<S> {
 
     pub fn history(&self, path: &Utf8Path) -> Result<Vec<FileVersion>> {
         let node_id = self.get_path_id(path).ok_or(Error::InvalidPath)?;
-        let data = self.storage.read().unwrap().get_block(&node_id.to_string());
-        let node = FileNode::load_from_vec(&data);
+        let node = self.open_fnode_with_id(node_id)?;
         if node.is_dir() {
             return Err(Error::IsDir);
         }
@@ -275,7 +395,11 @@ impl<S: Storage> FileSystem<S> {
             self.create_fnode(to, FileType::Dir)?
         };
 
-        let file_content = source.clone_current_content(self.storage.clone());
+        let file_content = source.clone_current_content(
+            self.storage.clone(),
+            self.block_options.key.as_ref(),
+            self.ids.new_id(),
+        )?;
         target.add_version(&file_content);
 
         Ok(())
@@ -320,7 +444,7 @@ impl<S: Storage> FileSystem<S> {
 
         node.clear_versions()?;
         let mut storage = self.storage.write().unwrap();
-        storage.del_block(&node.id.to_string());
+        storage.del_block(&node.id.into());
         // TODO: Remove FileContent and FileBlob
         // Be carreful to deduplication
 
@@ -343,7 +467,7 @@ impl<S: Storage> FileSystem<S> {
         // TODO: Check dir is empty
 
         let mut storage = self.storage.write().unwrap();
-        storage.del_block(&node.id.to_string());
+        storage.del_block(&node.id.into());
 
         Ok(())
     }
@@ -397,6 +521,22 @@ impl<S: Storage> FileSystem<S> {
             .apply_ops(ops);
     }
 
+    /// Walk the blocks reachable from the tree, each one verified
+    pub fn walk(&self) -> DagWalker<impl FnMut(&BlockAddress) -> shelter_block::Result<Vec<u8>>> {
+        let storage = self.storage.clone();
+        let root = self.config.tree_id.into();
+        let mut walker = DagWalker::new(link_registry(), [root], move |address| {
+            let storage = storage.read().unwrap();
+            // storages panic on a missing block
+            if !storage.is_exist(address) {
+                return Err(BlockError::MissingBlock(*address));
+            }
+            Ok(storage.get_block(address))
+        });
+        walker.set_key(self.block_options.key);
+        walker
+    }
+
     #[inline]
     pub fn destroy(&self) -> Result<()> {
         let mut storage = self.storage.write().unwrap();
@@ -405,6 +545,17 @@ impl<S: Storage> FileSystem<S> {
     }
 }
 
+/// Payload types of the file system blocks
+fn link_registry() -> LinkRegistry {
+    let mut registry = LinkRegistry::new();
+    registry
+        .register::<Tree>(BlockType::TREE)
+        .register::<FileNode>(BlockType::FILE)
+        .register::<FileContent>(BlockType::FVER)
+        .register::<FileIndex>(BlockType::INDX);
+    registry
+}
+
 // impl ShelterBlock for FileSystem {
 //     type ItemBlock = Self;
 
@@ -418,3 +569,29 @@ impl<S: Storage> FileSystem<S> {
 // }
 
 // TODO[epic=tests] Make Filesystem integration tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shelter_storage::{MemoryStorage, XChaCha};
+
+    #[test]
+    fn actor_ids() {
+        let storage = MemoryStorage::new(XChaCha::new(3, 1 << 8));
+        let options = FileSystemOptions::REPO_VERSIONED | FileSystemOptions::REPO_ACTOR_IDS;
+        let mut fs = FileSystem::new(options, storage);
+        fs.set_actor("replica");
+        fs.init("actor", "password").unwrap();
+        let machine = BlockId::with_actor("replica").machine();
+
+        let tree = fs.tree.clone().unwrap();
+        assert_eq!(tree.read().unwrap().actor(), "replica");
+        assert_eq!(tree.read().unwrap().id.machine(), machine);
+        assert_eq!(fs.config().id.machine(), machine);
+        let node = fs
+            .create_fnode(Utf8Path::new("/file"), FileType::File)
+            .unwrap();
+        assert_eq!(node.id.machine(), machine);
+        assert_eq!(fs.ids().new_id().machine(), machine);
+    }
+}
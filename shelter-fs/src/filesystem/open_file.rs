@@ -35,5 +35,13 @@ pub(super) fn open_file<S: Storage>(
     // } else {
     //     SeekFrom::Start(0)
     // };
-    Ok(File::new(open_options, fs.storage.clone(), file_node))
+    Ok(File::new(
+        open_options,
+        fs.storage.clone(),
+        fs.cdc().clone(),
+        *fs.block_options(),
+        fs.inline_limit(),
+        fs.ids().clone(),
+        file_node,
+    ))
 }
@@ -0,0 +1,344 @@
+//! Blocks frozen in `tests/fixtures`, written before the payload schema
+//! versions. They must stay readable by every release.
+//!
+//! File nodes, contents and trees are legacy (bincode) blocks with the
+//! payload layouts of the first release. The repository config didn't exist
+//! then, its fixture has the first layout stored in a block. [`generate`]
+//! writes the fixtures again from frozen copies of these layouts and of the
+//! block encodings, it must reproduce them byte for byte:
+//! `cargo test -p shelter-fs generate -- --ignored`.
+use super::file_content::{BlockAddressV0, FileContentV0};
+use super::{FileContent, FileNode, FileType, Tree};
+use crate::repository::RepositoryConfig;
+use crate::time::Time;
+use bincode::Options;
+use serde::Serialize;
+use shelter_block::{Block, BlockAddress, BlockId, MultiHashCode, ShelterBlock};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryFrom;
+
+const FILE_NODE: &[u8] = include_bytes!("../../tests/fixtures/file_node.v0.bin");
+const FILE_CONTENT: &[u8] = include_bytes!("../../tests/fixtures/file_content.v0.bin");
+const TREE: &[u8] = include_bytes!("../../tests/fixtures/tree.v0.bin");
+const REPOSITORY_CONFIG: &[u8] = include_bytes!("../../tests/fixtures/repository_config.v0.bin");
+
+const NODE_ID: BlockId = BlockId::from_bytes(*b"fixture node");
+const CONTENT_ID: BlockId = BlockId::from_bytes(*b"fixture data");
+const TREE_ID: BlockId = BlockId::from_bytes(*b"fixture tree");
+const ROOT_ID: BlockId = BlockId::from_bytes(*b"fixture root");
+const CONFIG_ID: BlockId = BlockId::from_bytes(*b"fixture conf");
+
+/// Chunks of the fixture file content
+const CHUNKS: [&[u8]; 2] = [b"chunk", b"inline"];
+
+/// Load a fixture, upgraded to the current schema
+///
+/// While the schema of `T` is the one of the fixture, the payload must be
+/// encoded identically: a failure means the layout changed without bumping
+/// [`ShelterBlock::SCHEMA_VERSION`].
+fn load<T: ShelterBlock<ItemBlock = T>>(fixture: &[u8]) -> T {
+    let block = Block::try_deserialize(fixture).unwrap();
+    assert_eq!(block.schema, 0);
+    let item = T::try_load_from_vec(fixture).unwrap();
+    if T::SCHEMA_VERSION == block.schema {
+        assert_eq!(
+            item.try_get_block_data().unwrap(),
+            block.try_get_data().unwrap().as_ref()
+        );
+    }
+    item
+}
+
+fn chunk_address(chunk: &[u8]) -> BlockAddress {
+    BlockAddress::try_from(&MultiHashCode::Blake3.digest(chunk).unwrap()).unwrap()
+}
+
+#[test]
+fn file_node() {
+    let node: FileNode = load(FILE_NODE);
+    assert_eq!(node.id, NODE_ID);
+    assert_eq!(node.name, "fixture.txt");
+    assert_eq!(node.file_type, FileType::File);
+    let history = node.history();
+    assert_eq!(history.len(), 1);
+    assert_eq!((history[0].version(), history[0].len()), (0, 11));
+    assert_eq!(node.get_current_block_id(), CONTENT_ID);
+}
+
+#[test]
+fn file_content() {
+    let content: FileContent = load(FILE_CONTENT);
+    assert_eq!(content.id, CONTENT_ID);
+    assert_eq!((content.len, content.depth), (11, 0));
+    let addresses: Vec<_> = CHUNKS.iter().map(|chunk| chunk_address(chunk)).collect();
+    assert_eq!(content.links(), addresses);
+    let refs = &content.block_address;
+    assert_eq!((refs[1].offset(), refs[1].len()), (5, 6));
+    assert!(!refs[1].is_inline());
+}
+
+#[test]
+fn tree() {
+    let tree: Tree = load(TREE);
+    assert_eq!(tree.id, TREE_ID);
+    assert!(tree.links().is_empty());
+    let root = tree
+        .replica
+        .tree()
+        .find(&"/".into())
+        .map(|node| *node.metadata());
+    assert_eq!(root, Some(ROOT_ID));
+}
+
+#[test]
+fn repository_config() {
+    let config: RepositoryConfig = load(REPOSITORY_CONFIG);
+    assert_eq!(config.id, CONFIG_ID);
+    assert_eq!(config.name, "fixture");
+    assert_eq!(config.inline_limit, 1024);
+    assert_eq!(config.tree_id, BlockId::get_magic());
+}
+
+// The generator only uses the frozen layouts below and `FileContentV0`, so
+// changing a payload or the block encoding doesn't change the fixtures.
+// `BlockId` and `Time` are kept, their layouts are the ones of the upgrades.
+
+/// Multihash of the legacy blocks
+#[derive(Serialize)]
+struct MultiHashV0 {
+    code: u32,
+    #[serde(with = "serde_bytes")]
+    digest: Vec<u8>,
+}
+
+/// Block encoded with bincode, see the legacy blocks of `FORMAT.md`
+#[derive(Serialize)]
+struct LegacyBlock {
+    signature: (char, char, char, char),
+    mh: MultiHashV0,
+    block_type: u32, // index of the type in the first `BlockType` enum
+    #[serde(with = "serde_bytes")]
+    data: Vec<u8>,
+}
+
+fn blake3(data: &[u8]) -> Vec<u8> {
+    MultiHashCode::Blake3.digest(data).unwrap().digest
+}
+
+fn legacy_block<T: Serialize>(block_type: u32, payload: &T) -> Vec<u8> {
+    let data = bincode::options().serialize(payload).unwrap();
+    let block = LegacyBlock {
+        signature: ('S', 'B', 'V', '1'),
+        mh: MultiHashV0 {
+            code: 0x1e,
+            digest: blake3(&data),
+        },
+        block_type,
+        data,
+    };
+    bincode::options().serialize(&block).unwrap()
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+/// Unsigned block of the fourth encoding version, uncompressed and
+/// addressed with blake3, see the layout of `FORMAT.md`
+fn v4_block<T: Serialize>(block_type: u64, payload: &T) -> Vec<u8> {
+    let data = bincode::options().serialize(payload).unwrap();
+    let digest = blake3(&data);
+    let mut buf = b"SBV1".to_vec();
+    buf.push(0x04); // version
+    put_varint(&mut buf, 0x00); // identity compression
+    put_varint(&mut buf, 0x1e); // blake3
+    put_varint(&mut buf, digest.len() as u64);
+    buf.extend_from_slice(&digest);
+    put_varint(&mut buf, block_type);
+    put_varint(&mut buf, 0); // schema
+    put_varint(&mut buf, data.len() as u64);
+    buf.extend_from_slice(&data);
+    put_varint(&mut buf, 0x00); // unsigned
+    buf
+}
+
+/// Layout of the first file node, unchanged since
+#[derive(Serialize)]
+struct FileNodeV0 {
+    id: BlockId,
+    name: String,
+    file_type: FileTypeV0,
+    version: usize,
+    versions: Vec<FileVersionV0>,
+    ctime: Time,
+    mtime: Time,
+}
+
+#[derive(Serialize)]
+enum FileTypeV0 {
+    File,
+}
+
+#[derive(Serialize)]
+struct FileVersionV0 {
+    id: BlockId,
+    version: usize,
+    len: usize,
+    ctime: Time,
+}
+
+/// Layout of the first tree, a `crdt_tree` 0.0.16 replica of paths
+#[derive(Serialize)]
+struct TreeV0 {
+    id: BlockId,
+    replica: TreeReplicaV0,
+}
+
+#[derive(Serialize)]
+struct TreeReplicaV0 {
+    state: StateV0,
+    time: ClockV0,
+    latest_time_by_replica: BTreeMap<String, ClockV0>,
+}
+
+#[derive(Serialize)]
+struct StateV0 {
+    log_op_list: Vec<LogOpMoveV0>,
+    triples: BTreeMap<String, TreeNodeV0>, // child => parent
+    children: BTreeMap<String, BTreeSet<String>>,
+}
+
+#[derive(Clone, Serialize)]
+struct ClockV0 {
+    actor_id: String,
+    counter: u64,
+}
+
+#[derive(Serialize)]
+struct LogOpMoveV0 {
+    timestamp: ClockV0,
+    parent_id: String,
+    metadata: BlockId,
+    child_id: String,
+    oldp: Option<TreeNodeV0>,
+}
+
+#[derive(Serialize)]
+struct TreeNodeV0 {
+    parent_id: String,
+    metadata: BlockId,
+}
+
+/// First layout of the repository config
+#[derive(Serialize)]
+struct RepositoryConfigV0 {
+    id: BlockId,
+    name: String,
+    min_size: usize,
+    avg_size: usize,
+    max_size: usize,
+    normalization: u8,
+    algorithm: u8,
+    keyed_chunking: bool,
+    hasher: u64,
+    compression: u64,
+    keyed_addressing: bool,
+    inline_limit: usize,
+    tree_id: BlockId,
+}
+
+#[test]
+#[ignore]
+fn generate() {
+    let mut offset = 0;
+    let block_address = CHUNKS
+        .iter()
+        .map(|chunk| {
+            let address = BlockAddressV0 {
+                address: chunk_address(chunk).legacy_name().unwrap(),
+                len: chunk.len(),
+                offset,
+            };
+            offset += chunk.len();
+            address
+        })
+        .collect();
+    let content = FileContentV0 {
+        id: CONTENT_ID,
+        len: offset,
+        ctime: Time::default(),
+        block_address,
+    };
+    let node = FileNodeV0 {
+        id: NODE_ID,
+        name: "fixture.txt".to_string(),
+        file_type: FileTypeV0::File,
+        version: 0,
+        versions: vec![FileVersionV0 {
+            id: CONTENT_ID,
+            version: 0,
+            len: content.len,
+            ctime: content.ctime,
+        }],
+        ctime: Time::default(),
+        mtime: Time::default(),
+    };
+    // the root `/` moved under the empty path
+    let clock = ClockV0 {
+        actor_id: "42".to_string(),
+        counter: 1,
+    };
+    let root = || TreeNodeV0 {
+        parent_id: String::new(),
+        metadata: ROOT_ID,
+    };
+    let tree = TreeV0 {
+        id: TREE_ID,
+        replica: TreeReplicaV0 {
+            state: StateV0 {
+                log_op_list: vec![LogOpMoveV0 {
+                    timestamp: clock.clone(),
+                    parent_id: String::new(),
+                    metadata: ROOT_ID,
+                    child_id: "/".to_string(),
+                    oldp: None,
+                }],
+                triples: BTreeMap::from([("/".to_string(), root())]),
+                children: BTreeMap::from([(String::new(), BTreeSet::from(["/".to_string()]))]),
+            },
+            time: clock.clone(),
+            latest_time_by_replica: BTreeMap::from([("42".to_string(), clock)]),
+        },
+    };
+    let config = RepositoryConfigV0 {
+        id: CONFIG_ID,
+        name: "fixture".to_string(),
+        min_size: 2048,
+        avg_size: 8192,
+        max_size: 65536,
+        normalization: 2,
+        algorithm: 0x01, // FastCDC 2016
+        keyed_chunking: true,
+        hasher: 0x1e,      // blake3
+        compression: 0x00, // identity
+        keyed_addressing: true,
+        inline_limit: 1024,
+        tree_id: BlockId::get_magic(),
+    };
+
+    // variant indexes of FILE, TREE and FVER in the first `BlockType` enum
+    let fixtures = [
+        ("file_node", legacy_block(2, &node)),
+        ("file_content", legacy_block(4, &content)),
+        ("tree", legacy_block(3, &tree)),
+        ("repository_config", v4_block(0x300031, &config)), // SBLK
+    ];
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    for (name, data) in fixtures {
+        std::fs::write(dir.join(format!("{}.v0.bin", name)), data).unwrap();
+    }
+}
@@ -1,13 +1,17 @@
+use super::IdGenerator;
 use camino::Utf8PathBuf;
 use crdt_tree::TreeReplica;
 use serde::{Deserialize, Serialize};
-use shelter_block::{BlockId, BlockType, ShelterBlock};
+use shelter_block::{BlockAddress, BlockId, BlockType, MutableBlock, ShelterBlock};
 use std::sync::{Arc, RwLock};
 
 type TypeId = Utf8PathBuf;
 type TypeMeta = BlockId;
 type TypeActor = String;
 
+/// Actor of the replicas created without one
+pub const DEFAULT_ACTOR: &str = "42";
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Tree {
     pub(super) id: BlockId,
@@ -15,36 +19,53 @@ pub struct Tree {
 }
 
 impl Tree {
-    /// Create new Tree with root path `/`
-    pub fn new() -> Self {
-        let id = BlockId::new();
-        let machine_id = String::from("42");
-        let mut replica = TreeReplica::new(machine_id);
-        let op = replica.opmove(Utf8PathBuf::new(), id, Utf8PathBuf::from("/"));
+    /// Create new Tree with root path `/`, replicated by `actor`
+    pub fn new(actor: &str, ids: &IdGenerator) -> Self {
+        let mut replica = TreeReplica::new(actor.to_string());
+        let op = replica.opmove(Utf8PathBuf::new(), ids.new_id(), Utf8PathBuf::from("/"));
         replica.apply_op(op);
 
         Self {
-            id: BlockId::new(),
+            id: ids.new_id(),
             replica,
         }
     }
+
+    /// Actor id of the replica
+    #[inline]
+    pub fn actor(&self) -> &str {
+        self.replica.id()
+    }
 }
 
 impl Default for Tree {
     fn default() -> Self {
-        Self::new()
+        Self::new(DEFAULT_ACTOR, &IdGenerator::default())
     }
 }
 
 impl ShelterBlock for Tree {
     type ItemBlock = Self;
 
-    fn get_block_id(&self) -> BlockId {
-        self.id
+    const BLOCK_TYPE: BlockType = BlockType::TREE;
+
+    /// File nodes of the tree, the root has no node
+    fn links(&self) -> Vec<BlockAddress> {
+        let tree = self.replica.tree();
+        let root = Utf8PathBuf::from("/");
+        let mut links = Vec::new();
+        tree.walk(&root, |tree, path, _| {
+            if let Some(node) = tree.find(path).filter(|_| *path != root) {
+                links.push((*node.metadata()).into());
+            }
+        });
+        links
     }
+}
 
-    fn get_block_type(&self) -> BlockType {
-        BlockType::TREE
+impl MutableBlock for Tree {
+    fn get_block_id(&self) -> BlockId {
+        self.id
     }
 }
 
@@ -52,5 +52,7 @@ mod time;
 
 // External API
 pub use error::Error;
+pub use fast_cdc::{Algorithm, ChunkerConfig};
 pub use filesystem::FileSystemOptions;
-pub use repository::Repository;
+pub use repository::{Repository, RepositoryConfig};
+pub use shelter_block::{Compression, MultiHashCode};
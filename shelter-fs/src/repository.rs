@@ -1,8 +1,12 @@
+mod config;
+
 use crate::error::Result;
 use crate::filesystem::{
     DirEntry, File, FileSystem, FileSystemOptions, FileType, FileVersion, Metadata, OpenOptions,
 };
 use camino::Utf8Path;
+pub use config::RepositoryConfig;
+use shelter_block::{BlockAddress, DagWalker};
 use shelter_storage::Storage;
 
 /// TODO[epic=doc] Repository
@@ -20,12 +24,36 @@ impl<S: Storage> Repository<S> {
             fs: FileSystem::new(config, storage),
         }
     }
+
+    /// Create new repository with specific settings
+    ///
+    /// `config` is only used when the repository is created, an existing
+    /// repository keeps the settings stored in its super block.
+    #[inline]
+    pub fn with_config(options: FileSystemOptions, config: RepositoryConfig, storage: S) -> Self {
+        Self {
+            fs: FileSystem::with_config(options, config, storage),
+        }
+    }
+
+    /// Set the actor id of the tree replica, used when the repository is
+    /// created
+    ///
+    /// With [`FileSystemOptions::REPO_ACTOR_IDS`], the ids of new blocks
+    /// embed a machine id derived from the actor instead of the host one.
+    #[inline]
+    pub fn set_actor(&mut self, actor: &str) {
+        self.fs.set_actor(actor);
+    }
+
     /// Initialize storage
     ///
     /// # Error
+    ///
+    /// Fails if the repository config is invalid.
     #[inline]
-    pub fn init(&mut self, name: &str, password: &str) {
-        self.fs.init(name, password);
+    pub fn init(&mut self, name: &str, password: &str) -> Result<()> {
+        self.fs.init(name, password)
     }
 
     /// Returns whether the path points at an existing entity in repository.
@@ -221,6 +249,14 @@ impl<S: Storage> Repository<S> {
         self.fs.rename(from.as_ref(), to.as_ref())
     }
 
+    /// Walk every block of the repository reachable from its tree.
+    ///
+    /// Blocks are verified and visited once, parents before children.
+    #[inline]
+    pub fn walk(&self) -> DagWalker<impl FnMut(&BlockAddress) -> shelter_block::Result<Vec<u8>>> {
+        self.fs.walk()
+    }
+
     /// Permanently destroy a repository specified by `uri`.
     ///
     /// This will permanently delete all files and directories in a repository
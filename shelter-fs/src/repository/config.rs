@@ -1,46 +1,181 @@
-use data_encoding::HEXUPPER;
-use orion::hash::{digest, Digest};
+use fast_cdc::{Algorithm, ChunkerConfig, ChunkingAlgorithm, GearTable};
 use serde::{Deserialize, Serialize};
+use shelter_block::{
+    AddressKey, BlockAddress, BlockId, BlockOptions, BlockType, Compression, MultiHashCode,
+    MutableBlock, ShelterBlock,
+};
+use shelter_storage::Storage;
+use std::sync::Arc;
 
-bitflags! {
-    #[derive(Serialize, Deserialize)]
-    pub struct RepositoryOptions: u32 {
-        const REPO_READ_ONLY     = 0b0000_0001;
-        const REPO_VERSIONED     = 0b0000_0010;
-        // const REPO_DEDUPLICATION = 0b0000_0010;
-    }
-}
+/// Context used to derive the chunker gear table key from the data key
+const GEAR_KEY_CONTEXT: &str = "rusty-shelter 2023-08-14 chunker gear key";
 
-// explicit `Default` implementation
-impl Default for RepositoryOptions {
-    fn default() -> RepositoryOptions {
-        RepositoryOptions::REPO_VERSIONED & RepositoryOptions::REPO_READ_ONLY
-    }
-}
+/// Max size of the chunks inlined in their file content by default
+const DEFAULT_INLINE_LIMIT: usize = 1024; // 1 KiB
+
+/// Context used to derive the block address key from the data key
+const ADDRESS_KEY_CONTEXT: &str = "rusty-shelter 2023-09-04 block address key";
 
-/// TODO[epic=doc] RepositoryConfig
+/// Repository wide settings.
+///
+/// The config is stored in the super block payload, so every writer of a
+/// repository uses the same settings (e.g. chunks file content identically).
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RepositoryConfig {
-    pub options: RepositoryOptions,
+    pub(crate) id: BlockId,
     pub name: String,
-    pub salt: String,
-    super_block_name: String,
+    pub chunker: ChunkerConfig,
+    pub algorithm: Algorithm, // chunking algorithm, kept for the repository lifetime
+    pub keyed_chunking: bool, // derive chunk boundaries from a repository secret
+    pub hasher: MultiHashCode, // hash function addressing new blocks
+    pub compression: Compression, // compression of new blocks
+    pub keyed_addressing: bool, // address blocks with a repository secret
+    pub inline_limit: usize,  // max size of the chunks inlined in their file content
+    pub(crate) tree_id: BlockId, // FileSystem tree block
 }
 
 impl RepositoryConfig {
-    pub fn new(name: &str, salt: &str, options: RepositoryOptions) -> RepositoryConfig {
-        let hash: Digest = digest(name.as_bytes()).unwrap();
+    pub fn new(chunker: ChunkerConfig) -> RepositoryConfig {
+        RepositoryConfig {
+            id: BlockId::new(),
+            name: String::new(),
+            chunker,
+            algorithm: Algorithm::default(),
+            keyed_chunking: true,
+            hasher: MultiHashCode::default(),
+            compression: Compression::default(),
+            keyed_addressing: true,
+            inline_limit: DEFAULT_INLINE_LIMIT,
+            tree_id: BlockId::get_magic(),
+        }
+    }
 
+    /// Config of a repository created before the repository config, whose
+    /// super block payload is its tree
+    ///
+    /// Such repositories have unkeyed chunks and addresses.
+    pub(crate) fn legacy(tree_id: BlockId) -> RepositoryConfig {
         RepositoryConfig {
-            options,
-            name: name.to_string(),
-            salt: salt.to_string(),
-            super_block_name: HEXUPPER.encode(hash.as_ref()),
+            keyed_chunking: false,
+            keyed_addressing: false,
+            tree_id,
+            ..Self::default()
         }
     }
 
     #[inline]
-    pub fn set_options(&mut self, options: RepositoryOptions) {
-        self.options = options;
+    pub fn set_chunker_config(&mut self, chunker: ChunkerConfig) {
+        self.chunker = chunker;
+    }
+
+    /// Set the chunking algorithm used when creating a repository
+    ///
+    /// An existing repository keeps the algorithm recorded in its config.
+    #[inline]
+    pub fn set_algorithm(&mut self, algorithm: Algorithm) {
+        self.algorithm = algorithm;
+    }
+
+    /// Enable or disable keyed chunking
+    ///
+    /// When enabled, the gear table used for chunking is derived from the
+    /// repository data key, so chunk lengths can't be used by the storage
+    /// provider to fingerprint known files.
+    #[inline]
+    pub fn set_keyed_chunking(&mut self, keyed_chunking: bool) {
+        self.keyed_chunking = keyed_chunking;
+    }
+
+    /// Set the hash function addressing the blocks written to the repository
+    ///
+    /// Blocks already stored keep their hash function, blocks are always
+    /// verified with the function recorded in their multihash.
+    #[inline]
+    pub fn set_hasher(&mut self, hasher: MultiHashCode) {
+        self.hasher = hasher;
+    }
+
+    /// Set the compression of the blocks written to the repository
+    ///
+    /// Blocks are stored uncompressed when compression doesn't make them
+    /// smaller, and always read whatever their compression.
+    #[inline]
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// Enable or disable keyed content addressing
+    ///
+    /// When enabled, blocks are addressed with BLAKE3 keyed by a secret
+    /// derived from the repository data key, so the storage provider can't
+    /// tell whether a known file is stored. Disable it for public or shared
+    /// data, whose addresses must be computable by anyone.
+    #[inline]
+    pub fn set_keyed_addressing(&mut self, keyed_addressing: bool) {
+        self.keyed_addressing = keyed_addressing;
+    }
+
+    /// Set the max size of the chunks inlined in their file content
+    ///
+    /// Small files and small chunks (e.g. the last chunk of a file) are
+    /// embedded in their file content instead of stored as blocks, saving
+    /// a block and a storage round trip. 0 stores every chunk.
+    #[inline]
+    pub fn set_inline_limit(&mut self, inline_limit: usize) {
+        self.inline_limit = inline_limit;
+    }
+
+    /// Settings of the unkeyed blocks, e.g. the config itself which is read
+    /// before the data key is known
+    pub(crate) fn block_options(&self) -> BlockOptions {
+        BlockOptions {
+            hasher: self.hasher,
+            compression: self.compression,
+            key: None,
+        }
+    }
+
+    /// Settings of the blocks written to the repository
+    pub(crate) fn new_block_options<S: Storage>(&self, storage: &S) -> BlockOptions {
+        let key = self
+            .keyed_addressing
+            .then(|| AddressKey::new(storage.derive_key(ADDRESS_KEY_CONTEXT)));
+        BlockOptions {
+            key,
+            ..self.block_options()
+        }
+    }
+
+    /// Create the chunking algorithm of the repository
+    pub(crate) fn new_cdc<S: Storage>(&self, storage: &S) -> Arc<dyn ChunkingAlgorithm> {
+        if self.keyed_chunking {
+            let gear = GearTable::from_key(&storage.derive_key(GEAR_KEY_CONTEXT));
+            self.algorithm.with_gear(self.chunker, gear)
+        } else {
+            self.algorithm.build(self.chunker)
+        }
+    }
+}
+
+impl Default for RepositoryConfig {
+    fn default() -> Self {
+        Self::new(ChunkerConfig::default())
+    }
+}
+
+impl ShelterBlock for RepositoryConfig {
+    type ItemBlock = Self;
+
+    const BLOCK_TYPE: BlockType = BlockType::SBLK;
+
+    /// Tree of the repository
+    fn links(&self) -> Vec<BlockAddress> {
+        vec![self.tree_id.into()]
+    }
+}
+
+impl MutableBlock for RepositoryConfig {
+    fn get_block_id(&self) -> BlockId {
+        self.id
     }
 }
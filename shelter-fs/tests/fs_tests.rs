@@ -3,8 +3,7 @@ extern crate shelter_storage;
 
 use camino::Utf8Path;
 use shelter_fs::{FileSystemOptions, Repository};
-use shelter_storage::{FileSystem, Storage, XChaCha};
-use std::io::{Read, Write};
+use shelter_storage::{FileSystem, XChaCha};
 use std::path::Path;
 
 #[test]
@@ -17,11 +16,11 @@ fn main() -> Result<(), std::io::Error> {
     let mut repo = Repository::new(FileSystemOptions::REPO_VERSIONED, file_storage);
 
     // 3. Init repository storage
-    repo.init("Jagu", "sengern");
+    repo.init("Jagu", "sengern").unwrap();
 
     // 3. Create a file
     let file_path = Utf8Path::new("/test.txt");
-    let mut file = repo.create_file(&file_path);
+    let _file = repo.create_file(file_path);
 
     // 4. Write into file
     // let content = "bonjour maman";
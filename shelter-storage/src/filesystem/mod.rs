@@ -1,5 +1,5 @@
 use crate::SuperBlock;
-use crate::{vio, Crypto, CryptoUtil, SecretKey, Storage};
+use crate::{vio, BlockAddress, Crypto, CryptoUtil, SecretKey, Storage};
 use moka::sync::Cache;
 use std::fs::{remove_file, File};
 use std::io::prelude::*;
@@ -10,7 +10,7 @@ pub struct FileSystem<C: Crypto + serde::Serialize> {
     super_block: SuperBlock<C>,
     master_key: Option<SecretKey>,
     data_key: Option<SecretKey>,
-    cache: Cache<String, Vec<u8>>,
+    cache: Cache<BlockAddress, Vec<u8>>,
 }
 
 impl<C: Crypto> FileSystem<C>
@@ -50,12 +50,35 @@ where
     fn save_super_block(&mut self) {
         let master_key = self.get_master_key();
         let data = self.super_block.serialize(master_key);
-        self.put_block(Self::SUPER_BLK_FILE_NAME, &data);
+        let mut file = File::create(self.base.join(Self::SUPER_BLK_FILE_NAME)).unwrap();
+        file.write_all(&data).unwrap();
+        file.sync_data().unwrap();
+    }
+
+    #[inline]
+    fn block_path(&self, address: &BlockAddress) -> PathBuf {
+        self.base.join(address.to_string())
+    }
+
+    /// Path of a stored block, falling back to its legacy name for blocks
+    /// written before multibase addresses
+    fn find_block_path(&self, address: &BlockAddress) -> PathBuf {
+        let path = self.block_path(address);
+        if path.exists() {
+            return path;
+        }
+        address
+            .legacy_name()
+            .map(|name| self.base.join(name))
+            .filter(|legacy| legacy.exists())
+            .unwrap_or(path)
     }
 
     #[inline]
     fn load_super_block(&mut self) {
-        let data: Vec<u8> = self.get_block(Self::SUPER_BLK_FILE_NAME);
+        let mut data = Vec::new();
+        let mut file = File::open(self.base.join(Self::SUPER_BLK_FILE_NAME)).unwrap();
+        file.read_to_end(&mut data).unwrap();
         let master_key = self.get_master_key();
         self.super_block = SuperBlock::deserialize(&data, master_key);
     }
@@ -105,7 +128,7 @@ where
 
     #[inline]
     fn is_init(&self) -> bool {
-        self.is_exist(Self::SUPER_BLK_FILE_NAME)
+        self.base.join(Self::SUPER_BLK_FILE_NAME).exists()
     }
 
     #[inline]
@@ -115,8 +138,13 @@ where
     }
 
     #[inline]
-    fn put_block(&mut self, cid: &str, data: &[u8]) {
-        let mut file = File::create(self.base.join(cid)).unwrap();
+    fn derive_key(&self, context: &str) -> [u8; 32] {
+        CryptoUtil::derive_key(self.get_data_key(), context)
+    }
+
+    #[inline]
+    fn put_block(&mut self, address: &BlockAddress, data: &[u8]) {
+        let mut file = File::create(self.block_path(address)).unwrap();
         let ciphertext = self
             .super_block
             .head
@@ -125,13 +153,19 @@ where
         file.write_all(&ciphertext).unwrap();
         // file.write_all(&data).unwrap();
         file.sync_data().unwrap();
+        // an updated block drops its legacy copy
+        if let Some(legacy) = address.legacy_name().map(|name| self.base.join(name)) {
+            if legacy.exists() {
+                remove_file(legacy).unwrap();
+            }
+        }
     }
 
     #[inline]
-    fn get_block(&self, cid: &str) -> Vec<u8> {
-        let buf = self.cache.get(&cid.to_owned()).unwrap_or_else(|| {
+    fn get_block(&self, address: &BlockAddress) -> Vec<u8> {
+        let buf = self.cache.get(address).unwrap_or_else(|| {
             let mut buf = Vec::new();
-            let mut file = File::open(self.base.join(cid)).unwrap();
+            let mut file = File::open(self.find_block_path(address)).unwrap();
             file.read_to_end(&mut buf).unwrap();
             buf
         });
@@ -142,13 +176,14 @@ where
     }
 
     #[inline]
-    fn del_block(&mut self, cid: &str) {
-        remove_file(cid).unwrap();
+    fn del_block(&mut self, address: &BlockAddress) {
+        self.cache.invalidate(address);
+        remove_file(self.find_block_path(address)).unwrap();
     }
 
     #[inline]
-    fn is_exist(&self, cid: &str) -> bool {
-        Path::new(cid).exists()
+    fn is_exist(&self, address: &BlockAddress) -> bool {
+        self.find_block_path(address).exists()
     }
 
     #[inline]
@@ -2,10 +2,12 @@
 #[macro_use]
 extern crate serde_derive;
 extern crate bincode;
+extern crate blake3;
 extern crate moka;
 extern crate orion;
 extern crate serde;
 extern crate serde_bytes;
+extern crate shelter_block;
 
 mod cipher;
 mod filesystem;
@@ -18,6 +20,7 @@ pub use cipher::Cipher;
 pub use filesystem::FileSystem;
 pub use memory::MemoryStorage;
 use orion::aead::SecretKey;
+pub use shelter_block::BlockAddress;
 use std::sync::{Arc, RwLock};
 use super_block::SuperBlock;
 pub use xchacha::XChaCha;
@@ -38,13 +41,17 @@ pub trait Storage: Send + Sync {
     // save payload into the super block
     fn save_payload(&mut self, payload: &[u8]);
 
+    // derive a repository secret from the data key, `context` must be
+    // unique for each usage of the derived key
+    fn derive_key(&self, context: &str) -> [u8; 32];
+
     // block read/write, can be buffered
     // storage doesn't need to gurantee update is persistent
-    fn get_block(&self, cid: &str) -> Vec<u8>;
-    fn put_block(&mut self, cid: &str, data: &[u8]);
-    fn del_block(&mut self, cid: &str);
+    fn get_block(&self, address: &BlockAddress) -> Vec<u8>;
+    fn put_block(&mut self, address: &BlockAddress, data: &[u8]);
+    fn del_block(&mut self, address: &BlockAddress);
 
-    fn is_exist(&self, cid: &str) -> bool;
+    fn is_exist(&self, address: &BlockAddress) -> bool;
 
     // flush blocks
     // storage must gurantee write is persistent
@@ -73,6 +80,10 @@ impl CryptoUtil {
     pub fn gen_secret_key() -> SecretKey {
         SecretKey::default()
     }
+
+    pub fn derive_key(key: &SecretKey, context: &str) -> [u8; 32] {
+        blake3::derive_key(context, key.unprotected_as_bytes())
+    }
 }
 
 /// Dummy storage
@@ -105,22 +116,27 @@ impl Storage for DummyStorage {
     }
 
     #[inline]
-    fn get_block(&self, _cid: &str) -> Vec<u8> {
+    fn derive_key(&self, _context: &str) -> [u8; 32] {
+        unimplemented!()
+    }
+
+    #[inline]
+    fn get_block(&self, _address: &BlockAddress) -> Vec<u8> {
         unimplemented!()
     }
 
     #[inline]
-    fn put_block(&mut self, _cid: &str, _data: &[u8]) {
+    fn put_block(&mut self, _address: &BlockAddress, _data: &[u8]) {
         unimplemented!()
     }
 
     #[inline]
-    fn del_block(&mut self, _cid: &str) {
+    fn del_block(&mut self, _address: &BlockAddress) {
         unimplemented!()
     }
 
     #[inline]
-    fn is_exist(&self, _cid: &str) -> bool {
+    fn is_exist(&self, _address: &BlockAddress) -> bool {
         unimplemented!()
     }
 
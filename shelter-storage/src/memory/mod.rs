@@ -1,10 +1,11 @@
 use crate::SuperBlock;
-use crate::{Crypto, CryptoUtil, SecretKey, Storage};
+use crate::{BlockAddress, Crypto, CryptoUtil, SecretKey, Storage};
 use std::collections::HashMap;
 
 pub struct MemoryStorage<C: Crypto + serde::Serialize> {
     super_block: SuperBlock<C>,
-    block_map: HashMap<String, Vec<u8>>,
+    super_blk: Vec<u8>,
+    block_map: HashMap<BlockAddress, Vec<u8>>,
     master_key: Option<SecretKey>,
     data_key: Option<SecretKey>,
 }
@@ -13,12 +14,11 @@ impl<C: Crypto> MemoryStorage<C>
 where
     C: serde::de::DeserializeOwned,
 {
-    const SUPER_BLK_KEY: &'static str = "super_blk";
-
     pub fn new(crypto: C) -> Self {
         let super_block = SuperBlock::new(crypto);
         Self {
             super_block,
+            super_blk: Vec::new(),
             block_map: HashMap::new(),
             master_key: None, // used to encrypt super block
             data_key: None,   // used to encrypt data block
@@ -42,15 +42,13 @@ where
     #[inline]
     fn save_super_block(&mut self) {
         let master_key = self.get_master_key();
-        let data = self.super_block.serialize(master_key);
-        self.put_block(Self::SUPER_BLK_KEY, &data);
+        self.super_blk = self.super_block.serialize(master_key);
     }
 
     #[inline]
     fn load_super_block(&mut self) {
-        let data: Vec<u8> = self.get_block(Self::SUPER_BLK_KEY);
         let master_key = self.get_master_key();
-        self.super_block = SuperBlock::deserialize(&data, master_key);
+        self.super_block = SuperBlock::deserialize(&self.super_blk, master_key);
     }
 }
 
@@ -103,20 +101,25 @@ where
     }
 
     #[inline]
-    fn put_block(&mut self, cid: &str, data: &[u8]) {
+    fn derive_key(&self, context: &str) -> [u8; 32] {
+        CryptoUtil::derive_key(self.get_data_key(), context)
+    }
+
+    #[inline]
+    fn put_block(&mut self, address: &BlockAddress, data: &[u8]) {
         let ciphertext = self
             .super_block
             .head
             .crypto
             .encrypt_with_key(self.get_data_key(), data);
-        self.block_map.insert(cid.to_owned(), ciphertext);
+        self.block_map.insert(*address, ciphertext);
     }
 
     #[inline]
-    fn get_block(&self, cid: &str) -> Vec<u8> {
+    fn get_block(&self, address: &BlockAddress) -> Vec<u8> {
         let buf = self
             .block_map
-            .get(cid)
+            .get(address)
             .expect("To get block referenced by cid");
         self.super_block
             .head
@@ -125,15 +128,15 @@ where
     }
 
     #[inline]
-    fn del_block(&mut self, cid: &str) {
+    fn del_block(&mut self, address: &BlockAddress) {
         self.block_map
-            .remove(cid)
+            .remove(address)
             .expect("To remove block referenced by cid");
     }
 
     #[inline]
-    fn is_exist(&self, cid: &str) -> bool {
-        self.block_map.contains_key(cid)
+    fn is_exist(&self, address: &BlockAddress) -> bool {
+        self.block_map.contains_key(address)
     }
 
     #[inline]
@@ -1,10 +1,6 @@
 use super::{Cipher, Crypto};
 use orion::{aead, kdf};
 
-// Re export SecretKey
-pub use aead::SecretKey;
-pub use kdf::Salt;
-
 // Crypto utility
 #[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct XChaCha {
@@ -83,6 +79,7 @@ impl Default for XChaCha {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::SecretKey;
 
     #[test]
     fn enc_dec() {
@@ -93,7 +90,7 @@ mod tests {
 
         // encryption
         let out = crypto.encrypt_with_key(&key, &data);
-        assert!(out.len() > 0);
+        assert!(!out.is_empty());
 
         // decryption
         let ret = crypto.decrypt_with_key(&key, &out);
@@ -1,12 +1,15 @@
+extern crate shelter_block;
 extern crate shelter_storage;
 
-use shelter_storage::{MemoryStorage, Storage, XChaCha};
+use shelter_block::BlockId;
+use shelter_storage::{BlockAddress, FileSystem, MemoryStorage, Storage, XChaCha};
+use std::fs::rename;
 
 #[test]
 fn main() {
     // 1. Configure crypto params
     // let crypto = Crypto::default();
-    let crypto = XChaCha::new(256, 3);
+    let crypto = XChaCha::new(3, 1 << 8);
 
     // 2. Create fs storage struct
     // #[encrypt(...params)]
@@ -17,11 +20,43 @@ fn main() {
 
     // 4. Write into memory storage
     let block = "my data".as_bytes();
-    memory_storage.put_block("test", block);
+    let address = BlockAddress::from(BlockId::new());
+    memory_storage.put_block(&address, block);
 
     // 5. Get back the block
-    let block2 = memory_storage.get_block("test");
+    let block2 = memory_storage.get_block(&address);
 
     // Compare
-    assert_eq!(block, &block2)
+    assert_eq!(block, &block2);
+    assert!(memory_storage.is_exist(&address));
+    memory_storage.del_block(&address);
+    assert!(!memory_storage.is_exist(&address));
+
+    // 6. Derive repository secrets
+    let key = memory_storage.derive_key("test");
+    assert_eq!(key, memory_storage.derive_key("test"));
+    assert_ne!(key, memory_storage.derive_key("other test"));
+}
+
+#[test]
+fn legacy_names() {
+    let base = std::env::temp_dir().join(format!("shelter-legacy-{}", BlockId::new()));
+    let mut storage = FileSystem::new(&base, XChaCha::new(3, 1 << 8), 16);
+    storage.init("sengern".as_bytes(), "payload".as_bytes());
+
+    // a block written before multibase addresses, named by its id
+    let id = BlockId::new();
+    let address = BlockAddress::from(id);
+    storage.put_block(&address, b"old data");
+    rename(base.join(address.to_string()), base.join(id.to_string())).unwrap();
+    assert!(storage.is_exist(&address));
+    assert_eq!(storage.get_block(&address), b"old data");
+
+    // updating it moves it to its new name
+    storage.put_block(&address, b"new data");
+    assert!(!base.join(id.to_string()).exists());
+    storage.del_block(&address);
+    assert!(!storage.is_exist(&address));
+
+    std::fs::remove_dir_all(base).unwrap();
 }